@@ -0,0 +1,82 @@
+import json
+import time
+
+from bs4 import BeautifulSoup
+
+from typing import Dict, Generator, List
+
+from services.source import LikesSource
+from syncer.model import Track
+
+
+class BandcampService(LikesSource):
+    """Tracks of Bandcamp fan collection (purchases).
+
+        Bandcamp has no public API for collections, the endpoints used by bandcamp.com pages and mobile app are,
+        so they may change without notice. Tracks of purchased albums are synced too.
+    """
+    account = 'bandcamp'
+    title = 'Bandcamp'
+    site_url = 'https://bandcamp.com'
+
+    def __init__(self, username, logger) -> None:
+        super().__init__(logger)
+        self.username = username
+
+    def _get_fan_id(self) -> int:
+        response = self.session.get(f'{self.site_url}/{self.username}')
+        response.raise_for_status()
+
+        # fan page keeps its data as JSON in `data-blob` attribute
+        blob = BeautifulSoup(response.text, 'html.parser').find(id='pagedata')['data-blob']
+        return json.loads(blob)['fan_data']['fan_id']
+
+    def _get_album_tracks(self, band_id: int, album_id: int) -> List[Dict]:
+        response = self.session.get(
+            f'{self.site_url}/api/mobile/24/tralbum_details',
+            params={'band_id': band_id, 'tralbum_id': album_id, 'tralbum_type': 'a'},
+        )
+        response.raise_for_status()
+        return response.json().get('tracks', [])
+
+    @classmethod
+    def _to_track(cls, item: Dict, track: Dict) -> Dict:
+        return Track(
+            artist=track.get('band_name') or item['band_name'],
+            id=cls.get_track_id(track['track_id']),
+            name=track['title'],
+            url=item.get('item_url'),
+        ).dict()
+
+    def get_liked_tracks(self) -> Generator[Dict, None, None]:
+        fan_id = self._get_fan_id()
+        older_than_token = f'{int(time.time())}::a::'
+        fetched_count = 0
+
+        while True:
+            response = self.session.post(
+                f'{self.site_url}/api/fancollection/1/collection_items',
+                json={'fan_id': fan_id, 'older_than_token': older_than_token, 'count': 50},
+            )
+            response.raise_for_status()
+            data = response.json()
+
+            fetched_count += len(data['items'])
+            self.logger.info(f'fetched {fetched_count} collection items from Bandcamp')
+
+            # scraped items change without notice, one malformed item or track doesn't stop the sync
+            for item in data['items']:
+                try:
+                    if item['tralbum_type'] == 't':
+                        tracks = [{'track_id': item['item_id'], 'title': item['item_title']}]
+                    else:
+                        tracks = self._get_album_tracks(item['band_id'], item['item_id'])
+                except (KeyError, TypeError) as e:
+                    self.logger.warning(f'skip invalid Bandcamp collection item {item.get("item_id")}: {e}')
+                    continue
+
+                yield from self._to_tracks(tracks, lambda track: self._to_track(item, track), id_key='track_id')
+
+            if not data.get('more_available'):
+                break
+            older_than_token = data['last_token']
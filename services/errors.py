@@ -0,0 +1,107 @@
+"""Classification of errors raised by service clients.
+
+    Kinds tell whether failed call might succeed later (rate limits, network errors)
+    or needs user attention (auth, not found, broken data).
+"""
+
+from enum import Enum
+from typing import Dict
+
+import pylast
+import requests
+import spotipy
+from pydantic import ValidationError
+
+
+class ErrorKind(str, Enum):
+    AUTH = 'auth'
+    RATE_LIMIT = 'rate_limit'
+    NETWORK = 'network'
+    NOT_FOUND = 'not_found'
+    DATA = 'data'
+    UNKNOWN = 'unknown'
+
+
+RETRYABLE_KINDS = {ErrorKind.RATE_LIMIT, ErrorKind.NETWORK}
+
+# process exit codes, 1 is left for unexpected errors and 2 is used by argparse
+EXIT_CODES = {
+    ErrorKind.AUTH: 3,
+    ErrorKind.RATE_LIMIT: 4,
+    ErrorKind.NETWORK: 5,
+    ErrorKind.NOT_FOUND: 6,
+    ErrorKind.DATA: 7,
+    ErrorKind.UNKNOWN: 1,
+}
+
+# https://www.last.fm/api/errorcodes
+LASTFM_ERROR_KINDS = {
+    '4': ErrorKind.AUTH,
+    '6': ErrorKind.NOT_FOUND,
+    '9': ErrorKind.AUTH,
+    '10': ErrorKind.AUTH,
+    '11': ErrorKind.NETWORK,
+    '14': ErrorKind.AUTH,
+    '16': ErrorKind.NETWORK,
+    '26': ErrorKind.AUTH,
+    '29': ErrorKind.RATE_LIMIT,
+}
+
+
+class ServiceError(Exception):
+    def __init__(self, message: str, kind: ErrorKind) -> None:
+        super().__init__(message)
+        self.kind = kind
+
+
+class TrackSyncError(Exception):
+    """Failure of one synced track, `phase` is the sync step which failed, e.g. `match` or `love`."""
+
+    def __init__(self, track: Dict, target: str, phase: str, source: Exception) -> None:
+        super().__init__(
+            f'{phase} failed in {target} for {track["artist"]} - {track["name"]} ({track["id"]}): {source}'
+        )
+        self.track = track
+        self.target = target
+        self.phase = phase
+        self.source = source
+
+
+def kind_by_http_status(status: int) -> ErrorKind:
+    if status in (401, 403):
+        return ErrorKind.AUTH
+    if status == 404:
+        return ErrorKind.NOT_FOUND
+    if status == 429:
+        return ErrorKind.RATE_LIMIT
+    if status >= 500:
+        return ErrorKind.NETWORK
+    return ErrorKind.DATA
+
+
+def classify_error(error: Exception) -> ErrorKind:
+    if isinstance(error, TrackSyncError):
+        return classify_error(error.source)
+    if isinstance(error, ServiceError):
+        return error.kind
+    if isinstance(error, pylast.WSError):
+        return LASTFM_ERROR_KINDS.get(str(error.get_id()), ErrorKind.DATA)
+    if isinstance(error, (pylast.NetworkError, pylast.MalformedResponseError)):
+        return ErrorKind.NETWORK
+    if isinstance(error, spotipy.oauth2.SpotifyOauthError):
+        return ErrorKind.AUTH
+    if isinstance(error, spotipy.SpotifyException):
+        return kind_by_http_status(error.http_status)
+    if isinstance(error, requests.HTTPError) and error.response is not None:
+        return kind_by_http_status(error.response.status_code)
+    if isinstance(error, (requests.ConnectionError, requests.Timeout)):
+        return ErrorKind.NETWORK
+    # invalid service data, built-in errors like KeyError are bugs, they stay unknown so they are never taken
+    # for not found tracks
+    if isinstance(error, ValidationError):
+        return ErrorKind.DATA
+    return ErrorKind.UNKNOWN
+
+
+def is_retryable(error: Exception) -> bool:
+    return classify_error(error) in RETRYABLE_KINDS
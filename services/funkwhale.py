@@ -0,0 +1,46 @@
+from pydantic import SecretStr
+
+from typing import Dict, Generator
+
+from services.source import LikesSource
+from syncer.model import Track
+
+
+class FunkwhaleService(LikesSource):
+    """Favorite tracks of Funkwhale instance user."""
+    account = 'funkwhale'
+    title = 'Funkwhale'
+
+    def __init__(self, url, token: SecretStr, logger) -> None:
+        super().__init__(logger)
+        self.url = url.rstrip('/')
+        self.session.headers['Authorization'] = f'Bearer {token.get_secret_value()}'
+
+    def _to_track(self, favorite: Dict) -> Dict:
+        track = favorite['track']
+        return Track(
+            artist=track['artist']['name'],
+            id=self.get_track_id(track['id']),
+            name=track['title'],
+            url=f'{self.url}/library/tracks/{track["id"]}',
+            mbid=track.get('mbid'),
+            added_at=favorite.get('creation_date'),
+        ).dict()
+
+    def get_liked_tracks(self) -> Generator[Dict, None, None]:
+        # https://docs.funkwhale.audio/developer/api/index.html
+        next_url = f'{self.url}/api/v1/favorites/tracks/'
+        params = {'scope': 'me', 'page_size': 50}
+        fetched_count = 0
+
+        while next_url:
+            response = self.session.get(next_url, params=params)
+            response.raise_for_status()
+            data = response.json()
+            # next page url has all query params
+            next_url, params = data['next'], None
+
+            fetched_count += len(data['results'])
+            self.logger.info(f'fetched {fetched_count}/{data["count"]} from Funkwhale API')
+
+            yield from self._to_tracks(data['results'], self._to_track)
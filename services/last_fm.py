@@ -1,29 +1,83 @@
 import pylast
 
+from pydantic import SecretStr
 from typing import Dict, Generator, Iterator, List, Optional, Tuple
 
+from syncer.logs import add_secrets
+
 
 class LastFmService:
-    def __init__(self, user, password, api_key, api_secret, logger) -> None:
+    network_class = pylast.LastFMNetwork
+
+    def __init__(self, user, password: SecretStr, api_key: SecretStr, api_secret: SecretStr, logger) -> None:
         logger.info(f'{self} created')
         self.logger = logger 
-        self.network = pylast.LastFMNetwork(
-            api_key=api_key,
-            api_secret=api_secret,
+        self.network = self.network_class(
+            api_key=api_key.get_secret_value(),
+            api_secret=api_secret.get_secret_value(),
             username=user,
-            password_hash=pylast.md5(password),
+            password_hash=pylast.md5(password.get_secret_value()),
         )
+        # session key is created by pylast, it is as good as password
+        add_secrets(self.network.session_key, self.network.password_hash)
  
+    def reauthenticate(self):
+        """Creates new session, e.g. when session key was revoked during long sync."""
+        self.network = self.network_class(
+            api_key=self.network.api_key,
+            api_secret=self.network.api_secret,
+            username=self.network.username,
+            password_hash=self.network.password_hash,
+        )
+        add_secrets(self.network.session_key)
+
+    def get_user_name(self) -> str:
+        return self.network.get_authenticated_user().get_name()
+
     def get_track(self, artist_name: str, track_name: str) -> pylast.Track:
         track = self.network.get_track(artist=artist_name, title=track_name)
         # check that track exists
         track.is_streamable()
         return track
 
+    def get_corrected_track(self, artist_name: str, track_name: str) -> pylast.Track:
+        """Returns track with artist and song names corrected by LastFM, e.g. misspelled ones."""
+        artist_name = self.network.get_artist(artist_name).get_correction() or artist_name
+        track_name = self.network.get_track(artist=artist_name, title=track_name).get_correction() or track_name
+        return self.get_track(artist_name, track_name)
+
+    def search_tracks(self, artist_name: str, track_name: str) -> List[pylast.Track]:
+        return self.network.search_for_track(artist_name, track_name).get_next_page()
+
+    def get_track_by_mbid(self, mbid: str) -> pylast.Track:
+        track = self.network.get_track_by_mbid(mbid)
+        # check that track exists
+        track.is_streamable()
+        return track
+
+    def dump_track(self, track: pylast.Track) -> Dict:
+        return {'artist': track.artist.name, 'name': track.get_name()}
+
+    def load_track(self, data: Dict) -> pylast.Track:
+        # pylast doesn't call API until track info is requested
+        return self.network.get_track(artist=data['artist'], title=data['name'])
+
     def like_track(self, track: pylast.Track):
         track.love()
 
+    def unlike_track(self, track: pylast.Track):
+        track.unlove()
+
+    def scrobble_track(self, track: pylast.Track, timestamp: int):
+        # https://www.last.fm/api/show/track.scrobble, scrobbles older than 14 days are ignored by LastFM
+        self.network.scrobble(artist=track.artist.name, title=track.get_name(), timestamp=timestamp)
+
     def get_liked_tracks(self, limit=50) -> Generator:
         user = self.network.get_authenticated_user()
         tracks = user.get_loved_tracks(limit=limit)
-        return tracks
\ No newline at end of file
+        return tracks
+
+    def get_top_tracks(self, period: str = pylast.PERIOD_OVERALL, limit=None) -> List[pylast.TopItem]:
+        """Returns user top tracks, TopItem.weight is play count."""
+        user = self.network.get_authenticated_user()
+        return user.get_top_tracks(period=period, limit=limit)
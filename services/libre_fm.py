@@ -0,0 +1,12 @@
+import pylast
+from pydantic import SecretStr
+
+from services.last_fm import LastFmService
+
+
+class LibreFmService(LastFmService):
+    """Libre.fm speaks LastFM API, api key and secret are not checked there."""
+    network_class = pylast.LibreFMNetwork
+
+    def __init__(self, user, password: SecretStr, logger) -> None:
+        super().__init__(user, password, api_key=SecretStr(''), api_secret=SecretStr(''), logger=logger)
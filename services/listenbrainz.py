@@ -0,0 +1,88 @@
+from pydantic import SecretStr
+from typing import Dict, Generator
+
+from services.errors import ErrorKind, ServiceError
+from syncer.identity import new_session
+
+
+class ListenBrainzService:
+    api_url = 'https://api.listenbrainz.org/1'
+
+    def __init__(self, token: SecretStr, logger) -> None:
+        logger.info(f'{self} created')
+        self.logger = logger
+        self.session = new_session()
+        self.session.headers['Authorization'] = f'Token {token.get_secret_value()}'
+
+    def get_user_name(self) -> str:
+        # https://listenbrainz.readthedocs.io/en/latest/users/api/core.html#get--1-validate-token
+        response = self.session.get(f'{self.api_url}/validate-token')
+        response.raise_for_status()
+
+        data = response.json()
+        if not data.get('valid'):
+            raise ServiceError(f'ListenBrainz token is not valid: {data.get("message")}', ErrorKind.AUTH)
+        return data['user_name']
+
+    def get_track(self, artist_name: str, track_name: str) -> str:
+        """Returns MusicBrainz recording id of the track."""
+        # https://listenbrainz.readthedocs.io/en/latest/users/api/metadata.html
+        response = self.session.get(
+            f'{self.api_url}/metadata/lookup/',
+            params={'artist_name': artist_name, 'recording_name': track_name},
+        )
+        response.raise_for_status()
+
+        recording_mbid = response.json().get('recording_mbid')
+        if not recording_mbid:
+            raise ServiceError(f'track is not found in ListenBrainz: {artist_name} - {track_name}', ErrorKind.NOT_FOUND)
+        return recording_mbid
+
+    def get_loved_recordings(self) -> Generator[Dict, None, None]:
+        """Yields recordings loved by the user: {'mbid': ..., 'artist': ..., 'name': ...}."""
+        # https://listenbrainz.readthedocs.io/en/latest/users/api/recordings.html#get--1-feedback-user-(user_name)-get-feedback
+        user_name = self.get_user_name()
+        count = 100
+        offset = 0
+
+        while True:
+            response = self.session.get(
+                f'{self.api_url}/feedback/user/{user_name}/get-feedback',
+                params={'score': 1, 'count': count, 'offset': offset, 'metadata': 'true'},
+            )
+            response.raise_for_status()
+            feedback = response.json()['feedback']
+
+            for item in feedback:
+                metadata = item.get('track_metadata') or {}
+                yield {
+                    'mbid': item['recording_mbid'],
+                    'artist': metadata.get('artist_name'),
+                    'name': metadata.get('track_name'),
+                }
+
+            offset += len(feedback)
+            if len(feedback) < count:
+                break
+
+    def dump_track(self, recording_mbid: str) -> str:
+        return recording_mbid
+
+    def load_track(self, recording_mbid: str) -> str:
+        return recording_mbid
+
+    def like_track(self, recording_mbid: str):
+        # https://listenbrainz.readthedocs.io/en/latest/users/api/recordings.html
+        response = self.session.post(
+            f'{self.api_url}/feedback/recording-feedback',
+            json={'recording_mbid': recording_mbid, 'score': 1},
+        )
+        response.raise_for_status()
+
+    def unlike_track(self, recording_mbid: str):
+        # score 0 removes feedback
+        response = self.session.post(
+            f'{self.api_url}/feedback/recording-feedback',
+            json={'recording_mbid': recording_mbid, 'score': 0},
+        )
+        response.raise_for_status()
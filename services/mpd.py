@@ -0,0 +1,52 @@
+import socket
+
+from typing import Dict, Optional
+
+from pydantic import SecretStr
+
+
+class MpdService:
+    """Minimal MPD (or Mopidy with MPD frontend) client, only for getting the current song."""
+
+    def __init__(self, host, port, password: Optional[SecretStr], logger) -> None:
+        self.logger = logger
+        self.host = host
+        self.port = port
+        self.password = password
+
+    def _command(self, connection, reader, command: str) -> Dict[str, str]:
+        # https://mpd.readthedocs.io/en/latest/protocol.html
+        connection.sendall(f'{command}\n'.encode())
+        response = {}
+        for line in reader:
+            line = line.decode().rstrip('\n')
+            if line == 'OK':
+                return response
+            if line.startswith('ACK'):
+                raise Exception(f'MPD error: {line}')
+            key, _, value = line.partition(': ')
+            response[key] = value
+        raise Exception('MPD closed connection')
+
+    def get_current_song(self) -> Optional[Dict]:
+        """Returns {'artist': ..., 'name': ..., 'mbid': ..., 'file': ...} of the playing song or None."""
+        with socket.create_connection((self.host, self.port), timeout=10) as connection:
+            reader = connection.makefile('rb')
+            greeting = reader.readline().decode()
+            if not greeting.startswith('OK MPD'):
+                raise Exception(f'not MPD server: {greeting}')
+
+            if self.password:
+                self._command(connection, reader, f'password "{self.password.get_secret_value()}"')
+            if self._command(connection, reader, 'status').get('state') != 'play':
+                return None
+
+            song = self._command(connection, reader, 'currentsong')
+            if not song.get('Artist') or not song.get('Title'):
+                raise Exception(f'current song has no artist or title tags: {song.get("file")}')
+            return {
+                'artist': song['Artist'],
+                'name': song['Title'],
+                'mbid': song.get('MUSICBRAINZ_TRACKID'),
+                'file': song.get('file'),
+            }
@@ -0,0 +1,28 @@
+from typing import List
+
+from syncer.identity import new_session
+
+
+class MusicBrainzService:
+    api_url = 'https://musicbrainz.org/ws/2'
+
+    def __init__(self, logger) -> None:
+        self.logger = logger
+        # MusicBrainz requires meaningful User-Agent, see syncer/identity.py
+        self.session = new_session()
+
+    def get_recording_ids(self, isrc: str) -> List[str]:
+        """Returns MusicBrainz recording ids (MBIDs) of the ISRC."""
+        response = self.session.get(f'{self.api_url}/isrc/{isrc}', params={'fmt': 'json'})
+        if response.status_code == 404:
+            return []
+        response.raise_for_status()
+        return [recording['id'] for recording in response.json()['recordings']]
+
+    def get_isrcs(self, recording_id: str) -> List[str]:
+        """Returns ISRCs of MusicBrainz recording."""
+        response = self.session.get(f'{self.api_url}/recording/{recording_id}', params={'fmt': 'json', 'inc': 'isrcs'})
+        if response.status_code == 404:
+            return []
+        response.raise_for_status()
+        return response.json().get('isrcs', [])
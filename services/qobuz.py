@@ -0,0 +1,49 @@
+from pydantic import SecretStr
+
+from typing import Dict, Generator
+
+from services.source import LikesSource
+from syncer.model import Track
+
+
+class QobuzService(LikesSource):
+    """Favorite tracks of Qobuz user. ISRCs make mbid matching precise."""
+    account = 'qobuz'
+    title = 'Qobuz'
+    api_url = 'https://www.qobuz.com/api.json/0.2'
+
+    def __init__(self, app_id, user_auth_token: SecretStr, logger) -> None:
+        super().__init__(logger)
+        self.session.headers['X-App-Id'] = app_id
+        self.session.headers['X-User-Auth-Token'] = user_auth_token.get_secret_value()
+
+    @classmethod
+    def _to_track(cls, track: Dict) -> Dict:
+        return Track(
+            artist=(track.get('performer') or track['album']['artist'])['name'],
+            id=cls.get_track_id(track['id']),
+            name=track['title'],
+            isrc=track.get('isrc'),
+            duration_ms=track['duration'] * 1000 if track.get('duration') is not None else None,
+            explicit=track.get('parental_warning'),
+        ).dict()
+
+    def get_liked_tracks(self) -> Generator[Dict, None, None]:
+        limit = 100
+        offset = 0
+
+        while True:
+            response = self.session.get(
+                f'{self.api_url}/favorite/getUserFavorites',
+                params={'type': 'tracks', 'limit': limit, 'offset': offset},
+            )
+            response.raise_for_status()
+            tracks = response.json()['tracks']
+
+            offset += len(tracks['items'])
+            self.logger.info(f'fetched {offset}/{tracks["total"]} from Qobuz API')
+
+            yield from self._to_tracks(tracks['items'], self._to_track)
+
+            if not tracks['items'] or offset >= tracks['total']:
+                break
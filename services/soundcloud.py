@@ -0,0 +1,50 @@
+from pydantic import SecretStr
+
+from typing import Dict, Generator
+
+from services.source import LikesSource
+from syncer.model import Track
+
+
+class SoundCloudService(LikesSource):
+    """Liked tracks of SoundCloud user.
+
+        Uploader names often differ from artist names, artist from publisher metadata is used when it is known.
+    """
+    account = 'soundcloud'
+    title = 'SoundCloud'
+    api_url = 'https://api.soundcloud.com'
+
+    def __init__(self, token: SecretStr, logger) -> None:
+        super().__init__(logger)
+        self.session.headers['Authorization'] = f'OAuth {token.get_secret_value()}'
+
+    @classmethod
+    def _to_track(cls, track: Dict) -> Dict:
+        metadata = track.get('publisher_metadata') or {}
+        return Track(
+            artist=metadata.get('artist') or track['user']['username'],
+            id=cls.get_track_id(track['id']),
+            name=track['title'],
+            isrc=metadata.get('isrc'),
+            url=track.get('permalink_url'),
+            duration_ms=track.get('duration'),
+        ).dict()
+
+    def get_liked_tracks(self) -> Generator[Dict, None, None]:
+        # https://developers.soundcloud.com/docs/api/explorer/open-api#/me/get_me_likes_tracks
+        next_url = f'{self.api_url}/me/likes/tracks'
+        params = {'limit': 50, 'linked_partitioning': 'true'}
+        fetched_count = 0
+
+        while next_url:
+            response = self.session.get(next_url, params=params)
+            response.raise_for_status()
+            data = response.json()
+            # next page url has all query params
+            next_url, params = data.get('next_href'), None
+
+            fetched_count += len(data['collection'])
+            self.logger.info(f'fetched {fetched_count} from SoundCloud API')
+
+            yield from self._to_tracks(data['collection'], self._to_track)
@@ -0,0 +1,34 @@
+from pydantic import ValidationError
+
+from typing import Any, Callable, Dict, Generator, Iterable
+
+from syncer.identity import new_session
+
+
+class LikesSource:
+    """Service other than Spotify with liked tracks, they are synced like Spotify liked tracks.
+
+        `account` is used as source name in pipelines and accounts attribution, track ids are prefixed with it,
+        so they never clash with Spotify ids in syncer state.
+    """
+    account = ''
+    # service name in log messages
+    title = ''
+
+    def __init__(self, logger) -> None:
+        logger.info(f'{self} created')
+        self.logger = logger
+        self.session = new_session()
+
+    @classmethod
+    def get_track_id(cls, item_id: Any) -> str:
+        return f'{cls.account}:{item_id}'
+
+    def _to_tracks(self, items: Iterable[Dict], to_track: Callable[[Dict], Dict],
+                   id_key: str = 'id') -> Generator[Dict, None, None]:
+        """Converts API items to tracks, one malformed item (e.g. without artist) doesn't stop the sync."""
+        for item in items:
+            try:
+                yield to_track(item)
+            except (KeyError, TypeError, ValidationError) as e:
+                self.logger.warning(f'skip invalid {self.title} track {item.get(id_key)}: {e}')
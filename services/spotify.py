@@ -1,48 +1,249 @@
+import json
 import logging
-import time
+import os
+from concurrent.futures import ThreadPoolExecutor
+from datetime import datetime
 
 import spotipy
-from spotipy.oauth2 import SpotifyOAuth
-from typing import Dict, Generator, Iterator, List, Optional, Tuple
+from pydantic import SecretStr, ValidationError
+from spotipy.cache_handler import CacheFileHandler
+from spotipy.oauth2 import SpotifyClientCredentials, SpotifyOAuth, SpotifyPKCE
+from typing import Dict, Generator, Iterable, Iterator, List, Optional, Tuple
+
+from services.errors import ErrorKind, ServiceError
+from syncer.identity import new_session
+from syncer.model import Track
+
+
+class PrivateCacheFileHandler(CacheFileHandler):
+    """Creates token cache owner-only, spotipy writes it with default umask, so a fresh token would be readable
+        by others until syncer secures state files.
+    """
+
+    def save_token_to_cache(self, token_info):
+        try:
+            with os.fdopen(os.open(self.cache_path, os.O_WRONLY | os.O_CREAT | os.O_TRUNC, 0o600), 'w') as f:
+                os.fchmod(f.fileno(), 0o600)
+                f.write(json.dumps(token_info))
+        except OSError as e:
+            # spotipy only warns when the cache can't be written too
+            logging.getLogger(__name__).warning(f"couldn't write token to cache at {self.cache_path}: {e}")
 
 
 class SpotifyService:
-    def __init__(self, client_id, client_secret, logger: logging.Logger) -> None:
+    redirect_uri = 'http://localhost:8888/callback'
+    scope = 'user-read-email user-library-read user-library-modify user-top-read'
+
+    def __init__(self, client_id, client_secret: Optional[SecretStr], logger: logging.Logger,
+                 market: Optional[str] = None, account: Optional[str] = None) -> None:
         self.logger = logger
-        self.spotify = spotipy.Spotify(auth_manager=SpotifyOAuth(client_id=client_id,
-                                                                 client_secret=client_secret,
-                                                                 show_dialog=True,
-                                                                 redirect_uri="http://localhost:8888/callback",
-                                                                 scope="user-read-email user-library-read"))
+        # every extra account has own auth cache, default one uses spotipy `.cache`
+        self.account = account or 'default'
+        cache_handler = PrivateCacheFileHandler(cache_path=f'.cache-{account}' if account else '.cache')
+        # ISO 3166-1 alpha-2 country code or `from_token`, used for track relinking
+        self.market = market
+        if client_secret:
+            auth_manager = SpotifyOAuth(client_id=client_id, client_secret=client_secret.get_secret_value(),
+                                        show_dialog=True, cache_handler=cache_handler, redirect_uri=self.redirect_uri,
+                                        scope=self.scope, requests_session=new_session())
+        else:
+            # Authorization Code with PKCE flow, app needs no client secret
+            auth_manager = SpotifyPKCE(client_id=client_id, cache_handler=cache_handler,
+                                       redirect_uri=self.redirect_uri, scope=self.scope, requests_session=new_session())
+        self.spotify = spotipy.Spotify(auth_manager=auth_manager, requests_session=new_session())
+        # catalog lookups (tracks, artists, search) need no user, so commands doing only them, e.g. `sync --track`
+        # or `import-* --dry-run`, work without interactive OAuth. Client credentials flow needs client secret.
+        self.catalog = spotipy.Spotify(
+            auth_manager=SpotifyClientCredentials(client_id=client_id, client_secret=client_secret.get_secret_value(),
+                                                  requests_session=new_session()),
+            requests_session=new_session(),
+        ) if client_secret else None
+
+    @property
+    def metadata_client(self) -> spotipy.Spotify:
+        # `from_token` market is the market of authorized user
+        return self.spotify if self.market == 'from_token' or not self.catalog else self.catalog
+
+    @staticmethod
+    def _to_track(track: Dict) -> Dict:
+        """Returns track in stable JSON shape, see syncer.model.Track.
+
+            Podcast episodes, audiobook chapters and local files have the same shape, so they are skipped
+            by sync with a reason instead of failing, see `item_type`.
+        """
+        item_type = 'local' if track.get('is_local') else track.get('type', 'track')
+        # episodes have show instead of artists
+        artist = (track.get('artists') or [{'name': (track.get('show') or {}).get('name', ''), 'id': None}])[0]
+        return Track(
+            artist=artist['name'],
+            artist_id=artist['id'],
+            name=track['name'],
+            # local files have no id
+            id=track['id'] or track['uri'],
+            isrc=track.get('external_ids', {}).get('isrc'),
+            url=track.get('external_urls', {}).get('spotify'),
+            external_ids=track.get('external_ids', {}),
+            duration_ms=track.get('duration_ms', 0),
+            popularity=track.get('popularity', 0),
+            explicit=track.get('explicit', False),
+            # is_playable and restrictions are returned only when market is given
+            is_playable=track.get('is_playable', True),
+            restriction=track.get('restrictions', {}).get('reason'),
+            linked_from=(track.get('linked_from') or {}).get('id'),
+            added_at=track.get('added_at'),
+            item_type=item_type,
+        ).dict()
+
+    def _to_tracks(self, tracks: Iterable[Dict]) -> Generator[Dict, None, None]:
+        """Converts API tracks skipping invalid ones, e.g. with empty names."""
+        for track in tracks:
+            try:
+                yield self._to_track(track)
+            except ValidationError as e:
+                self.logger.warning(f'skip invalid Spotify track {track.get("id")}: {e}')
+
+    def get_user_name(self) -> str:
+        # https://developer.spotify.com/documentation/web-api/reference/#/operations/get-current-users-profile
+        user = self.spotify.current_user()
+        return f'{user.get("display_name") or user["id"]} ({user["id"]})'
 
-    def get_liked_tracks(self) -> Generator[Dict, None, None]:
+    def get_liked_tracks(self, market: Optional[str] = None, workers: int = 4) -> Generator[Dict, None, None]:
         # https://developer.spotify.com/documentation/web-api/reference/endpoint-get-users-saved-tracks
-        all_fetched = False
-        tracks = []
-        fetched_count = 0
         limit = 50
-        offset = 0
+        market = market or self.market
+
+        def fetch_page(offset: int) -> List[Dict]:
+            return self.spotify.current_user_saved_tracks(limit=limit, offset=offset, market=market)['items']
 
         try:
-            while not all_fetched:
-                time.sleep(0.2)
-                response = self.spotify.current_user_saved_tracks(limit=limit, offset=offset)
-                new_tracks = response['items']
-
-                fetched_count += len(new_tracks) 
-                all_fetched = fetched_count == response['total']
-                offset += len(new_tracks)
-
-                self.logger.info(f'fetched {fetched_count}/{response["total"]} from Spotify API')
-
-                for track in new_tracks:
-                    yield {
-                        'artist': track['track']['artists'][0]['name'],
-                        'name': track['track']['name'],
-                        'id':track['track']['id'],
-                    }
+            # first page reveals total, the rest are fetched concurrently and yielded in order
+            response = self.spotify.current_user_saved_tracks(limit=limit, offset=0, market=market)
+            total = response['total']
+            fetched_count = len(response['items'])
+            self.logger.info(f'fetched {fetched_count}/{total} from Spotify API')
+            yield from self._to_tracks({**item['track'], 'added_at': item['added_at']} for item in response['items'])
+
+            with ThreadPoolExecutor(max_workers=workers) as executor:
+                for new_tracks in executor.map(fetch_page, range(limit, total, limit)):
+                    fetched_count += len(new_tracks)
+                    self.logger.info(f'fetched {fetched_count}/{total} from Spotify API')
+
+                    yield from self._to_tracks({**item['track'], 'added_at': item['added_at']} for item in new_tracks)
         except spotipy.oauth2.SpotifyOauthError as e:
-            raise Exception(f'{e}. Try to remove auth cache. Run rm .cache in current dir')
+            raise ServiceError(
+                f'{e}. Try to remove auth cache. Run rm .cache in current dir (.cache-<account> for extra accounts)',
+                ErrorKind.AUTH,
+            ) from e
+
+    def get_library_state(self) -> Dict:
+        """Returns liked tracks count and the latest like with one call, unchanged state means nothing to sync."""
+        response = self.spotify.current_user_saved_tracks(limit=1, offset=0, market=self.market)
+        latest = response['items'][0] if response['items'] else None
+        return {
+            'total': response['total'],
+            'latest_added_at': latest['added_at'] if latest else None,
+            'latest_track_id': latest['track']['id'] if latest else None,
+        }
+
+    def get_recently_liked_tracks(self, since: datetime) -> Generator[Dict, None, None]:
+        """Yields tracks liked after `since` (UTC), pages are fetched one by one until an older like."""
+        # saved tracks are ordered by like time, the latest first
+        limit = 50
+        offset = 0
+        since_added_at = since.strftime('%Y-%m-%dT%H:%M:%SZ')
+
+        while True:
+            response = self.spotify.current_user_saved_tracks(limit=limit, offset=offset, market=self.market)
+            items = [item for item in response['items'] if item['added_at'] >= since_added_at]
+            offset += len(response['items'])
+            self.logger.info(f'fetched {offset}/{response["total"]} from Spotify API, {len(items)} recent')
+
+            yield from self._to_tracks({**item['track'], 'added_at': item['added_at']} for item in items)
+
+            if len(items) < len(response['items']) or not response['items'] or offset >= response['total']:
+                break
+
+    def get_top_tracks(self, time_range: str = 'medium_term') -> Generator[Dict, None, None]:
+        # https://developer.spotify.com/documentation/web-api/reference/#/operations/get-users-top-artists-and-tracks
+        limit = 50
+        offset = 0
+
+        while True:
+            response = self.spotify.current_user_top_tracks(limit=limit, offset=offset, time_range=time_range)
+            if not response['items']:
+                break
+
+            offset += len(response['items'])
+            self.logger.info(f'fetched {offset}/{response["total"]} top tracks from Spotify API')
+
+            yield from self._to_tracks(response['items'])
+
+            if offset >= response['total']:
+                break
+
+    def get_tracks(self, track_ids: List[str]) -> Generator[Dict, None, None]:
+        # https://developer.spotify.com/documentation/web-api/reference/#/operations/get-several-tracks
+        limit = 50
+        for offset in range(0, len(track_ids), limit):
+            chunk = track_ids[offset:offset + limit]
+            # unknown ids are returned as nulls
+            for track_id, track in zip(chunk, self.metadata_client.tracks(chunk, market=self.market)['tracks']):
+                if track:
+                    yield from self._to_tracks([track])
+                else:
+                    self.logger.warning(f'track is not found in Spotify: {track_id}')
+
+    def find_album_track_ids(self, artist: str, album: str) -> Tuple[str, List[str]]:
+        """Returns `artist - album` name of the found album and ids of its tracks."""
+        # https://developer.spotify.com/documentation/web-api/reference/#/operations/search
+        albums = self.metadata_client.search(
+            q=f'album:{album} artist:{artist}', type='album', limit=10, market=self.market,
+        )['albums']['items']
+        # search is fuzzy, album of the same artist is preferred
+        albums.sort(key=lambda found: all(a['name'].lower() != artist.lower() for a in found['artists']))
+        if not albums:
+            raise ServiceError(f'album is not found in Spotify: {artist} - {album}', ErrorKind.NOT_FOUND)
+
+        # https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-albums-tracks
+        track_ids = []
+        limit = 50
+        while True:
+            response = self.metadata_client.album_tracks(
+                albums[0]['id'], limit=limit, offset=len(track_ids), market=self.market,
+            )
+            track_ids += [track['id'] for track in response['items']]
+            if not response['items'] or len(track_ids) >= response['total']:
+                break
+
+        return f'{albums[0]["artists"][0]["name"]} - {albums[0]["name"]}', track_ids
+
+    def get_artists_genres(self, artist_ids: List[str]) -> Dict[str, List[str]]:
+        # https://developer.spotify.com/documentation/web-api/reference/#/operations/get-multiple-artists
+        genres = {}
+        limit = 50
+        for offset in range(0, len(artist_ids), limit):
+            for artist in self.metadata_client.artists(artist_ids[offset:offset + limit])['artists']:
+                genres[artist['id']] = artist['genres']
+        return genres
+
+    def like_tracks(self, track_ids: List[str]):
+        # https://developer.spotify.com/documentation/web-api/reference/#/operations/save-tracks-user
+        limit = 50
+        for offset in range(0, len(track_ids), limit):
+            self.spotify.current_user_saved_tracks_add(tracks=track_ids[offset:offset + limit])
+
+    def remove_liked_tracks(self, track_ids: List[str]):
+        # https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-tracks-user
+        limit = 50
+        for offset in range(0, len(track_ids), limit):
+            self.spotify.current_user_saved_tracks_delete(tracks=track_ids[offset:offset + limit])
+
+    def search_tracks(self, query: str, limit: int = 50) -> Dict:
+        return self.metadata_client.search(q=query, type='track', limit=limit, market=self.market)
+
+    def find_track_by_isrc(self, isrc: str) -> Optional[Dict]:
+        items = self.search_tracks(f'isrc:{isrc}', limit=1)['tracks']['items']
+        return self._to_track(items[0]) if items else None
 
     def get_custom_liked_playlist(self, playlist_name: str = 'lastfm_liked') -> Optional[Dict]:
         
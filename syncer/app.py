@@ -3,7 +3,14 @@ import pickle
 import logging
 import time
 import os
-from typing import Dict, Generator, Iterator, List, Optional, Tuple
+from datetime import datetime, timedelta
+import re
+import runpy
+import stat
+import subprocess
+import sys
+import uuid
+from typing import Any, Callable, Dict, Generator, Iterable, Iterator, List, Optional, Set, Tuple
 
 import pylast
 
@@ -13,10 +20,41 @@ import pylast
 from fuzzywuzzy import fuzz
 
 from dependency_injector.wiring import inject, Provide
+from pydantic import SecretStr, ValidationError
+from services.errors import ErrorKind, TrackSyncError, classify_error, is_retryable
 from services.last_fm import LastFmService
+from services.mpd import MpdService
+from services.musicbrainz import MusicBrainzService
 from services.spotify import SpotifyService
 from syncer.di_containers import DIContainer, Settings
-from syncer.model import SyncTrack
+from syncer.identity import set_user_agent
+from syncer.logs import add_secrets, current_run_id, current_track_id, find_secrets, redact
+from syncer.matching import Match, MatcherChain, find_duplicates, fold_name
+from syncer.model import SyncTrack, Track
+from syncer.notifications import HeartbeatPinger, NtfyNotifier, SentryReporter, format_summary
+from syncer.pacing import AdaptiveDelay
+from syncer.usage import ApiUsage, BudgetExhaustedError, target_service
+
+
+# state files and Spotify token caches are readable only by the owner
+PRIVATE_FILE_MODE = 0o600
+# LastFM ignores older scrobbles, so loves of older likes are not backdated
+MAX_SCROBBLE_AGE = timedelta(days=14)
+# re-authentications of a target during one sync, credentials which are rejected again and again are wrong
+MAX_REAUTHENTICATIONS = 3
+
+
+class RunIdFilter(logging.Filter):
+    """Prefixes log messages written during sync with its run id."""
+
+    def __init__(self, run_id: str) -> None:
+        super().__init__()
+        self.run_id = run_id
+
+    def filter(self, record: logging.LogRecord) -> bool:
+        record.run_id = self.run_id
+        record.msg = f'[run {self.run_id}] {record.msg}'
+        return True
 
 
 class Syncer:
@@ -24,52 +62,1279 @@ class Syncer:
     def __init__(self, 
                  lastfm_service: LastFmService = Provide[DIContainer.lastfm_service],
                  spotify_service: SpotifyService = Provide[DIContainer.spotify_service],
+                 extra_spotify_services: List[SpotifyService] = Provide[DIContainer.extra_spotify_services],
+                 extra_sources: List[Any] = Provide[DIContainer.extra_sources],
+                 mpd_service: MpdService = Provide[DIContainer.mpd_service],
+                 extra_targets: Dict[str, Any] = Provide[DIContainer.extra_targets],
+                 pipelines: Dict[str, Dict] = Provide[DIContainer.config.pipelines],
+                 track_hook_path: Optional[str] = Provide[DIContainer.config.track_hook],
+                 hooks: Dict[str, Optional[str]] = Provide[DIContainer.config.hooks],
+                 love_top_min_plays: int = Provide[DIContainer.config.love_top_min_plays],
+                 matchers: List[str] = Provide[DIContainer.config.matchers],
+                 rewrite_rules: List[Dict] = Provide[DIContainer.config.rewrite_rules],
+                 confirm_confidence: float = Provide[DIContainer.config.confirm_confidence],
+                 min_confidence: Optional[float] = Provide[DIContainer.config.min_confidence],
+                 not_found_retry_days: int = Provide[DIContainer.config.not_found_retry_days],
+                 search_cache_days: int = Provide[DIContainer.config.search_cache_days],
+                 filters: Dict[str, Any] = Provide[DIContainer.config.filters],
+                 slow_love_interval_secs: float = Provide[DIContainer.config.slow_love_interval_secs],
+                 read_only: bool = Provide[DIContainer.config.read_only],
+                 ntfy_url: Optional[str] = Provide[DIContainer.config.ntfy_url],
+                 heartbeat_url: Optional[str] = Provide[DIContainer.config.heartbeat_url],
+                 sentry_dsn: Optional[SecretStr] = Provide[DIContainer.config.sentry_dsn],
+                 max_calls_per_run: Dict[str, int] = Provide[DIContainer.config.max_calls_per_run],
+                 backdate_scrobbles: bool = Provide[DIContainer.config.backdate_scrobbles],
+                 liked_since_days: Optional[int] = Provide[DIContainer.config.liked_since_days],
+                 mass_removal_ratio: float = Provide[DIContainer.config.mass_removal_ratio],
+                 abort_on_mass_removal: bool = Provide[DIContainer.config.abort_on_mass_removal],
                  logger: logging.Logger = Provide[DIContainer.logger],
                 ):
         self.lastfm_service = lastfm_service
         self.spotify_service = spotify_service
+        self.extra_spotify_services = extra_spotify_services
+        self.extra_sources = extra_sources
+        self.mpd_service = mpd_service
+        self.extra_targets = extra_targets
+        self.pipelines = pipelines
+        self.track_hook_path = track_hook_path
+        self.hooks = hooks
+        self.love_top_min_plays = love_top_min_plays
+        self.matcher_chain = MatcherChain(
+            matchers, logger, cache_ttl_days=search_cache_days, rewrite_rules=rewrite_rules,
+        )
+        self.confirm_confidence = confirm_confidence
+        self.min_confidence = min_confidence
+        self.not_found_retry_days = not_found_retry_days
+        self.filters = filters
+        self.slow_love_interval_secs = slow_love_interval_secs
+        self.read_only = read_only
+        self.notifier = NtfyNotifier(ntfy_url, logger) if ntfy_url else None
+        self.heartbeat = HeartbeatPinger(heartbeat_url, logger) if heartbeat_url else None
+        self.sentry = SentryReporter(sentry_dsn, logger) if sentry_dsn else None
+        self.api_usage = ApiUsage(max_calls_per_run, logger)
+        self.backdate_scrobbles = backdate_scrobbles
+        self.liked_since_days = liked_since_days
+        self.mass_removal_ratio = mass_removal_ratio
+        self.abort_on_mass_removal = abort_on_mass_removal
+        self.api_usage.install()
         self.logger = logger 
         self._cache_file = '.cache_processed'
+        self._attribution_file = '.cache_attribution'
+        self._snapshots_dir = '.snapshots'
+        self._matches_file = '.cache_matches'
+        self._not_found_file = '.cache_not_found'
+        # keys of `.cache_search` mixed up other recordings (e.g. live versions), its entries are not reused
+        self._search_cache_file = '.cache_search_v2'
+        self._genres_cache_file = '.cache_genres'
+        self._runs_file = '.sync_runs'
+        self._tombstones_file = '.cache_tombstones'
+        self._review_file = '.review_queue'
+        self._library_state_file = '.library_state'
+        self._library_sizes_file = '.library_sizes'
+        self._pruned_file = '.cache_pruned'
+        self._audit_log_file = '.audit_log'
+        self._api_usage_file = '.api_usage'
+        # `serve` schedule state, pipelines have own files
+        self._last_scheduled_run_file = '.last_scheduled_run'
+        self._secure_state_files()
+
+    def _get_private_files(self) -> List[str]:
+        """Returns existing files with credentials (Spotify tokens) or listening history, they are owner-only."""
+        paths = [path for paths in self._get_cache_files().values() for path in paths]
+        paths += [self._get_cache_file(target) for target in ['lastfm', *self.extra_targets]]
+        paths += [
+            self._attribution_file, self._matches_file, self._runs_file, self._tombstones_file, self._audit_log_file,
+            self._api_usage_file, self._review_file, self._library_sizes_file, self._pruned_file,
+            '.missed_spotify_tracks',
+        ]
+        if os.path.isdir(self._snapshots_dir):
+            paths += [os.path.join(self._snapshots_dir, name) for name in os.listdir(self._snapshots_dir)]
+        return [path for path in paths if os.path.isfile(path)]
+
+    def _secure_state_files(self):
+        """Warns about state files readable by others and makes them owner-only (except in read-only mode)."""
+        for path in self._get_private_files():
+            mode = stat.S_IMODE(os.stat(path).st_mode)
+            if not mode & 0o077:
+                continue
+
+            if self.read_only:
+                self.logger.warning(f'{path} is accessible by other users ({mode:o}), run `chmod 600 {path}`')
+            else:
+                self.logger.warning(f'{path} is accessible by other users ({mode:o}), changing mode to 600')
+                os.chmod(path, PRIVATE_FILE_MODE)
+
+    def _check_writable(self, action: str):
+        """Forbids changing state files and liked tracks in services in read-only mode."""
+        if self.read_only:
+            raise Exception(f'{action} is not allowed in read-only mode')
+
+    @staticmethod
+    def _read_json(path: str, default: Any) -> Any:
+        """Returns content of state file, `default` when it was not written yet."""
+        if not os.path.exists(path):
+            return default
+
+        with open(path) as f:
+            return json.load(f)
+
+    def _write_json(self, path: str, data: Any):
+        """Writes state file atomically, so interrupted sync never leaves it half written."""
+        self._check_writable(f'writing {path}')
+        tmp_path = f'{path}.tmp'
+        # state is created owner-only, mode of existing tmp file is fixed too
+        with os.fdopen(os.open(tmp_path, os.O_WRONLY | os.O_CREAT | os.O_TRUNC, PRIVATE_FILE_MODE), 'w') as f:
+            os.fchmod(f.fileno(), PRIVATE_FILE_MODE)
+            json.dump(data, f)
+            f.flush()
+            os.fsync(f.fileno())
+        os.replace(tmp_path, path)
+
+    def _audited(self, target: str, action: str, tracks: List[str], call: Callable[[], Any]) -> Any:
+        """Runs call changing liked tracks in target and records it in audit log, one entry per track."""
+        try:
+            result = call()
+        except Exception as e:
+            self._append_audit_log(target, action, tracks, f'error: {redact(str(e))}')
+            raise
+        self._append_audit_log(target, action, tracks, 'ok')
+        return result
+
+    def _append_audit_log(self, target: str, action: str, tracks: List[str], result: str):
+        # JSON lines are appended, so the log is never rewritten and survives interrupted syncs
+        now = datetime.now().isoformat(timespec='seconds')
+        run_id = current_run_id.get()
+        with os.fdopen(os.open(self._audit_log_file, os.O_WRONLY | os.O_CREAT | os.O_APPEND, PRIVATE_FILE_MODE), 'a') as f:
+            for track in tracks:
+                entry = {'at': now, 'run_id': run_id, 'target': target, 'action': action, 'track': track, 'result': result}
+                f.write(json.dumps(entry) + '\n')
+
+    def list_audit_log(self, target: Optional[str] = None, action: Optional[str] = None,
+                       limit: Optional[int] = None) -> List[Dict]:
+        """Returns audit log entries, the latest `limit` ones when limit is given."""
+        if not os.path.exists(self._audit_log_file):
+            return []
+
+        with open(self._audit_log_file) as f:
+            entries = [json.loads(line) for line in f if line.strip()]
+        entries = [
+            entry for entry in entries
+            if (not target or entry['target'] == target) and (not action or entry['action'] == action)
+        ]
+        return entries[-limit:] if limit else entries
 
-    def _load_processed_tracks(self) -> List[str]:
-        self.logger.info(f'download syncer cache: {self._cache_file}')
+    def _get_cache_file(self, target: str) -> str:
+        # LastFM cache keeps its original name, other targets have own cache files
+        return self._cache_file if target == 'lastfm' else f'{self._cache_file}_{target}'
 
-        with open(self._cache_file, 'r+') as f:
+    def _load_processed_tracks(self, target: str = 'lastfm') -> List[str]:
+        cache_file = self._get_cache_file(target)
+        self.logger.info(f'download syncer cache: {cache_file}')
+
+        if target != 'lastfm' and not os.path.exists(cache_file):
+            return []
+
+        with open(cache_file, 'r+') as f:
             saved_track_ids = f.read().strip() or '[]'
             return json.loads(saved_track_ids)
 
-    def _dump_processed_tracks(self, track_ids: List[str]):
-        self.logger.info(f'store syncer cache: {self._cache_file}')
-        with open(self._cache_file, 'w') as f:
-            return json.dump(track_ids, f)
+    def _dump_processed_tracks(self, track_ids: List[str], target: str = 'lastfm'):
+        cache_file = self._get_cache_file(target)
+        self.logger.info(f'store syncer cache: {cache_file}')
+        self._write_json(cache_file, track_ids)
 
-    def sync_spotify_likes_with_lastfm(self):
-        cached_track_ids = set(self._load_processed_tracks())
-        new_ids = set()
+    def _load_attribution(self) -> Dict[str, List[str]]:
+        return self._read_json(self._attribution_file, {})
+
+    def _dump_attribution(self, attribution: Dict[str, List[str]]):
+        self.logger.info(f'store accounts attribution: {self._attribution_file}')
+        self._write_json(self._attribution_file, attribution)
+
+    def _load_matches(self) -> Dict[str, Dict]:
+        """Returns how synced tracks were matched, keyed by `<target>:<spotify track id>`."""
+        return self._read_json(self._matches_file, {})
+
+    def _dump_matches(self, matches: Dict[str, Dict]):
+        self.logger.info(f'store matches: {self._matches_file}')
+        self._write_json(self._matches_file, matches)
+
+    def _record_match(self, matches: Dict[str, Dict], target: str, track_id: str, artist: str, name: str,
+                      method: str, confidence: float, run_id: Optional[str] = None, target_track: Any = None):
+        """Stores match of synced track, `target_track` is the loved track dumped by target service `dump_track`."""
+        matches[f'{target}:{track_id}'] = {
+            'run_id': run_id,
+            'target': target,
+            'track_id': track_id,
+            'artist': artist,
+            'name': name,
+            'method': method,
+            'confidence': confidence,
+            'loved_at': datetime.now().isoformat(timespec='seconds'),
+            # undo and prune unlove exactly this track
+            'target_track': target_track,
+        }
+
+    def _load_loved_track(self, service, match: Dict) -> Optional[Any]:
+        """Returns target track loved for the match, matches stored before loved tracks were recorded
+            are matched again.
+        """
+        if match.get('target_track') is not None:
+            return service.load_track(match['target_track'])
+
+        found = self.matcher_chain.match(service, {'artist': match['artist'], 'name': match['name']})
+        return found.target_track if found else None
+
+    @staticmethod
+    def _get_synced_tracks(tracks: List[Dict], matches: Dict[str, Dict], targets: Iterable[str],
+                           *synced_ids: Dict[str, Set[str]]) -> List[Dict]:
+        """Returns given tracks synced to any of targets and synced tracks which are not given (e.g. older likes
+            in `--since-days` sync) with artist and song name of their matches.
+        """
+        ids = {track_id for target_ids in synced_ids for target in targets for track_id in target_ids.get(target, ())}
+        given_ids = {track['id'] for track in tracks}
+        not_given = {
+            match['track_id']: {'id': match['track_id'], 'artist': match['artist'], 'name': match['name']}
+            for match in matches.values()
+            if match['target'] in targets and match['track_id'] in ids and match['track_id'] not in given_ids
+        }
+        return [track for track in tracks if track['id'] in ids] + list(not_given.values())
+
+    def _load_not_found(self) -> Dict[str, Dict]:
+        """Returns tracks not found in targets, keyed by `<target>:<spotify track id>`."""
+        return self._read_json(self._not_found_file, {})
+
+    def _dump_not_found(self, not_found: Dict[str, Dict]):
+        self.logger.info(f'store not found tracks: {self._not_found_file}')
+        self._write_json(self._not_found_file, not_found)
+
+    def _load_search_cache(self) -> Dict[str, Dict]:
+        return self._read_json(self._search_cache_file, {})
+
+    def _dump_search_cache(self, cache: Dict[str, Dict]):
+        self.logger.info(f'store search cache: {self._search_cache_file}')
+        now = datetime.now()
+        self._write_json(self._search_cache_file, {
+            key: value for key, value in cache.items()
+            if datetime.fromisoformat(value['expires_at']) > now
+        })
+
+    def _load_tombstones(self) -> Dict[str, Dict]:
+        """Returns tracks which must not be loved again, keyed by `<target>:<spotify track id>`."""
+        return self._read_json(self._tombstones_file, {})
+
+    def _dump_tombstones(self, tombstones: Dict[str, Dict]):
+        self.logger.info(f'store tombstones: {self._tombstones_file}')
+        self._write_json(self._tombstones_file, tombstones)
+
+    def list_tombstones(self) -> List[Dict]:
+        return sorted(self._load_tombstones().values(), key=lambda tombstone: tombstone['created_at'])
+
+    def remove_tombstones(self, track_ids: List[str]) -> List[Dict]:
+        """Allows syncing the tracks again, returns removed tombstones."""
+        tombstones = self._load_tombstones()
+        removed = [tombstones.pop(key) for key in list(tombstones) if key.split(':', 1)[1] in track_ids]
+        if removed:
+            self._dump_tombstones(tombstones)
+        return removed
+
+    def _load_review_queue(self) -> Dict[str, Dict]:
+        """Returns low confidence matches waiting for user review, keyed by `<target>:<spotify track id>`."""
+        return self._read_json(self._review_file, {})
+
+    def _dump_review_queue(self, review_queue: Dict[str, Dict]):
+        self.logger.info(f'store review queue: {self._review_file}')
+        self._write_json(self._review_file, review_queue)
+
+    def list_review_queue(self, target: Optional[str] = None) -> List[Dict]:
+        return sorted(
+            (match for match in self._load_review_queue().values() if not target or match['target'] == target),
+            key=lambda match: match['confidence'],
+        )
+
+    def _pop_reviewed(self, review_queue: Dict[str, Dict], track_ids: Optional[List[str]]) -> List[Dict]:
+        """Removes matches of given tracks (all of them when `track_ids` is None) from the queue."""
+        return [
+            review_queue.pop(key) for key in list(review_queue)
+            if track_ids is None or key.split(':', 1)[1] in track_ids
+        ]
+
+    def approve_reviewed(self, track_ids: Optional[List[str]] = None) -> List[Dict]:
+        """Loves queued candidates of given tracks (all queued when `track_ids` is None), returns loved ones."""
+        self._check_writable('review approve')
+        targets = {'lastfm': self.lastfm_service, **self.extra_targets}
+        review_queue = self._load_review_queue()
+        matches = self._load_matches()
+        approved = []
+        for match in self._pop_reviewed(review_queue, track_ids):
+            service = targets.get(match['target'])
+            if not service:
+                self.logger.warning(f'skip match of not configured target: {match}')
+                review_queue[f'{match["target"]}:{match["track_id"]}'] = match
+                continue
+
+            self.logger.info(f'set like for reviewed track in {match["target"]}: {match["candidate"]}')
+            self._audited(match['target'], 'love', [f'{match["artist"]} - {match["name"]} ({match["track_id"]})'],
+                          lambda: service.like_track(service.load_track(match['target_track'])))
+            self._record_match(
+                matches, match['target'], match['track_id'], match['artist'], match['name'], match['method'],
+                match['confidence'], run_id=match['run_id'], target_track=match['target_track'],
+            )
+            approved.append(match)
+
+        for target in {match['target'] for match in approved}:
+            cached_track_ids = set(self._load_processed_tracks(target))
+            approved_ids = {match['track_id'] for match in approved if match['target'] == target}
+            self._dump_processed_tracks(list(cached_track_ids | approved_ids), target)
+        if approved:
+            self._dump_matches(matches)
+        self._dump_review_queue(review_queue)
+        return approved
+
+    def reject_reviewed(self, track_ids: Optional[List[str]] = None) -> List[Dict]:
+        """Drops queued candidates of given tracks, the tracks become not found ones (see `unmatched list`).
+            Cached matches of rejected tracks are dropped too, so retries search targets again.
+        """
+        self._check_writable('review reject')
+        targets = {'lastfm': self.lastfm_service, **self.extra_targets}
+        review_queue = self._load_review_queue()
+        not_found = self._load_not_found()
+        self.matcher_chain.cache = self._load_search_cache()
+        rejected = self._pop_reviewed(review_queue, track_ids)
+        retry_after = (datetime.now() + timedelta(days=self.not_found_retry_days)).isoformat()
+        for match in rejected:
+            # cached matches of targets which aren't configured anymore are left to expire
+            if match['target'] in targets:
+                self.matcher_chain.forget(targets[match['target']], match)
+            not_found[f'{match["target"]}:{match["track_id"]}'] = {
+                'run_id': match['run_id'],
+                'target': match['target'],
+                'track_id': match['track_id'],
+                'artist': match['artist'],
+                'name': match['name'],
+                'retry_after': retry_after,
+                'attempts': [{
+                    'matcher': match['method'],
+                    'result': f'rejected {match["candidate"]} with confidence {match["confidence"]}',
+                }],
+            }
+
+        self._dump_not_found(not_found)
+        self._dump_review_queue(review_queue)
+        self._dump_search_cache(self.matcher_chain.cache)
+        return rejected
+
+    def _load_library_states(self) -> Dict[str, Dict]:
+        """Returns Spotify library states of the last complete syncs, keyed by account."""
+        return self._read_json(self._library_state_file, {})
+
+    def _dump_library_states(self, states: Dict[str, Dict]):
+        self.logger.info(f'store library states: {self._library_state_file}')
+        self._write_json(self._library_state_file, states)
+
+    def _load_library_sizes(self) -> Dict[str, Dict]:
+        """Returns Spotify liked tracks counts recorded by syncs, keyed by account."""
+        return self._read_json(self._library_sizes_file, {})
+
+    def _dump_library_sizes(self, sizes: Dict[str, Dict]):
+        self.logger.info(f'store library sizes: {self._library_sizes_file}')
+        self._write_json(self._library_sizes_file, sizes)
+
+    def load_last_scheduled_run(self, pipeline: Optional[str] = None) -> Optional[datetime]:
+        """Returns time of the last scheduled sync (or pipeline run) of `serve`."""
+        path = f'{self._last_scheduled_run_file}_{pipeline}' if pipeline else self._last_scheduled_run_file
+        if not os.path.exists(path):
+            return None
+
+        with open(path) as f:
+            content = f.read().strip()
+        # older versions stored plain time instead of JSON
+        return datetime.fromisoformat(json.loads(content) if content.startswith('"') else content)
+
+    def dump_last_scheduled_run(self, run_at: datetime, pipeline: Optional[str] = None):
+        path = f'{self._last_scheduled_run_file}_{pipeline}' if pipeline else self._last_scheduled_run_file
+        self._write_json(path, run_at.isoformat())
+
+    def _record_library_size(self, library_sizes: Dict[str, Dict], account: str, total: int):
+        library_sizes[account] = {'total': total, 'recorded_at': datetime.now().isoformat(timespec='seconds')}
+        self._dump_library_sizes(library_sizes)
+
+    def accept_library_sizes(self) -> Dict[str, int]:
+        """Records current Spotify library sizes as baselines of mass removal detection, e.g. after the user
+            removed many likes on purpose. Returns liked tracks count per account.
+        """
+        self._check_writable('accept-library-size')
+        library_sizes = self._load_library_sizes()
+        totals = {}
+        for service in [self.spotify_service] + self.extra_spotify_services:
+            totals[service.account] = service.get_library_state()['total']
+            self._record_library_size(library_sizes, service.account, totals[service.account])
+        return totals
+
+    def _find_mass_removal(self, library_sizes: Dict[str, Dict], account: str, total: int) -> Optional[str]:
+        """Returns warning when Spotify library is much smaller than at the last sync, e.g. API glitch returned
+            only a part of it.
+        """
+        previous = library_sizes.get(account)
+        if not previous or total >= previous['total'] * (1 - self.mass_removal_ratio):
+            return None
+        return (
+            f'Spotify library of {account} dropped from {previous["total"]} to {total} liked tracks '
+            f'since {previous["recorded_at"]}'
+        )
+
+    def _load_pruned(self) -> Dict[str, Dict]:
+        """Returns matches of tracks unloved by `prune --unlove` with `pruned_at`, keyed by `<target>:<spotify track id>`."""
+        return self._read_json(self._pruned_file, {})
+
+    def _dump_pruned(self, pruned: Dict[str, Dict]):
+        self.logger.info(f'store pruned tracks: {self._pruned_file}')
+        self._write_json(self._pruned_file, pruned)
+
+    def _load_genres_cache(self) -> Dict[str, List[str]]:
+        return self._read_json(self._genres_cache_file, {})
+
+    def _dump_genres_cache(self, genres: Dict[str, List[str]]):
+        self.logger.info(f'store artists genres cache: {self._genres_cache_file}')
+        self._write_json(self._genres_cache_file, genres)
+
+    def _get_cache_files(self) -> Dict[str, List[str]]:
+        """Returns files of caches which can be cleared without losing sync state, keyed by cache name."""
+        return {
+            'spotify-token': ['.cache'] + [f'.cache-{service.account}' for service in self.extra_spotify_services],
+            'search-cache': [self._search_cache_file],
+            'genres-cache': [self._genres_cache_file],
+            'not-found': [self._not_found_file],
+            'library-state': [self._library_state_file],
+        }
+
+    def get_caches_info(self) -> List[Dict]:
+        """Returns size, entries count and last modification time of every cache file."""
+        now = datetime.now()
+        info = []
+        for name, paths in self._get_cache_files().items():
+            for path in paths:
+                if not os.path.exists(path):
+                    info.append({'cache': name, 'file': path, 'size': 0, 'entries': 0, 'modified': 'never'})
+                    continue
+
+                with open(path) as f:
+                    data = json.load(f)
+                modified_at = datetime.fromtimestamp(os.path.getmtime(path))
+                info.append({
+                    'cache': name,
+                    'file': path,
+                    'size': os.path.getsize(path),
+                    # spotipy token cache is one object
+                    'entries': len(data) if name != 'spotify-token' else 1,
+                    'modified': f'{modified_at.isoformat(timespec="seconds")} ({(now - modified_at).days} days ago)',
+                })
+        return info
+
+    def clear_caches(self, name: str) -> List[str]:
+        """Removes files of the cache (or `all` caches), returns removed files."""
+        self._check_writable('clearing cache')
+        cache_files = self._get_cache_files()
+        if name != 'all' and name not in cache_files:
+            raise Exception(f'unknown cache: {name}, known: {sorted(cache_files)}')
+
+        removed = []
+        for path in sum([paths for cache, paths in cache_files.items() if name in (cache, 'all')], []):
+            if os.path.exists(path):
+                self.logger.info(f'remove cache: {path}')
+                os.remove(path)
+                removed.append(path)
+        return removed
+
+    def _filter_track(self, spotify_service: SpotifyService, track: Dict, artists_genres: Dict[str, List[str]],
+                      filters: Dict[str, Any]) -> Optional[str]:
+        """Returns reason why the track is skipped by filters (FILTERS_* settings with pipeline overrides)
+            or not being a track, None for synced tracks.
+        """
+        # podcast episodes and audiobooks would pollute LastFM, local files have no Spotify metadata
+        if track.get('item_type', 'track') != 'track':
+            return f'{track["item_type"]} is not a track'
+
+        min_duration_secs, min_popularity = filters['min_duration_secs'], filters['min_popularity']
+
+        # tracks of sources which don't know duration, popularity or explicit flag aren't filtered by them
+        duration_ms, popularity, explicit = track.get('duration_ms'), track.get('popularity'), track.get('explicit')
+        if min_duration_secs and duration_ms is not None and duration_ms < min_duration_secs * 1000:
+            return f'shorter than {min_duration_secs} seconds'
+        if min_popularity and popularity is not None and popularity < min_popularity:
+            return f'popularity is lower than {min_popularity}'
+        if filters['explicit'] == 'skip' and explicit:
+            return 'explicit'
+        if filters['explicit'] == 'only' and explicit is False:
+            return 'not explicit'
+
+        genres_include, genres_exclude = filters['genres_include'], filters['genres_exclude']
+
+        # genres are known only for Spotify artists
+        if (genres_include or genres_exclude) and track['artist_id']:
+            if track['artist_id'] not in artists_genres:
+                artists_genres.update(spotify_service.get_artists_genres([track['artist_id']]))
+
+            genres = artists_genres[track['artist_id']]
+            has_genre = lambda terms: any(term.lower() in genre for term in terms for genre in genres)
+
+            if genres_exclude and has_genre(genres_exclude):
+                return f'excluded genre in {genres}'
+            if genres_include and not has_genre(genres_include):
+                return f'no included genre in {genres}'
+
+    def love_album(self, artist: str, album: str, target_names: Optional[List[str]] = None) -> Dict:
+        """Loves all tracks of the album found in Spotify, they are synced as given tracks, so they are recorded
+            as synced even when they are not liked in Spotify.
+        """
+        found, track_ids = self.spotify_service.find_album_track_ids(artist, album)
+        self.logger.info(f'love {len(track_ids)} tracks of album: {found}')
+        return self.sync(track_ids=track_ids, target_names=target_names)
+
+    def retry_unmatched(self) -> Dict:
+        """Forgets tracks which were not found in targets and syncs them again."""
+        self._dump_not_found({})
+        return self.sync(full=True)
+
+    def list_unmatched(self, target: Optional[str] = None) -> List[Dict]:
+        """Returns tracks not found in targets with matcher attempts, tracks stored before attempts were recorded
+            have empty attempts.
+        """
+        return sorted(
+            (
+                {'attempts': [], **track} for track in self._load_not_found().values()
+                if not target or track['target'] == target
+            ),
+            key=lambda track: (track['target'], track['artist'].lower(), track['name'].lower()),
+        )
+
+    def retry_failed(self) -> Dict:
+        """Syncs again tracks which failed in the last sync run."""
+        runs = [run for run in self.list_runs() if run.get('summary')]
+        if not runs:
+            raise Exception('there are no finished sync runs')
+
+        failed_ids = sorted({failure['track_id'] for failure in runs[-1]['summary']['failures']})
+        if not failed_ids:
+            self.logger.info(f'there are no failed tracks in the last run {runs[-1]["run_id"]}')
+            return runs[-1]['summary']
+
+        return self.sync(resync=failed_ids)
+
+    def match_test(self, artist: str, name: str) -> Dict:
+        """Shows how the track is rewritten, what every matcher finds in LastFM and which match wins,
+            search cache is not used.
+        """
+        track = self.matcher_chain.rewrite({'artist': artist, 'name': name})
+        strategies = self.matcher_chain.explain(self.lastfm_service, track)
+        # the first found track wins, the same way as in sync
+        winner = next((strategy for strategy in strategies if strategy['found']), None)
+        return {'rewritten': f'{track["artist"]} - {track["name"]}', 'strategies': strategies, 'decision': winner}
+
+    def get_recently_loved(self, limit: int = 50) -> List[Dict]:
+        """Returns the latest loved tracks, matches stored before `loved_at` was recorded are skipped."""
+        matches = [match for match in self._load_matches().values() if match.get('loved_at')]
+        return sorted(matches, key=lambda match: match['loved_at'], reverse=True)[:limit]
+
+    def query_matches(self, confidence: Optional[str] = None, method: Optional[str] = None) -> List[Dict]:
+        """Returns stored matches filtered by confidence condition like `<0.8` or `>=0.9` and match method."""
+        matches = list(self._load_matches().values())
+
+        if confidence:
+            found = re.match(r'^\s*(<=|>=|<|>|=)?\s*([0-9.]+)\s*$', confidence)
+            if not found:
+                raise Exception(f'wrong confidence condition: {confidence}')
+
+            operator, value = found.group(1) or '=', float(found.group(2))
+            compare = {
+                '<': lambda c: c < value,
+                '<=': lambda c: c <= value,
+                '>': lambda c: c > value,
+                '>=': lambda c: c >= value,
+                '=': lambda c: c == value,
+            }[operator]
+            matches = [match for match in matches if compare(match['confidence'])]
+
+        if method:
+            matches = [match for match in matches if match['method'] == method]
+
+        return sorted(matches, key=lambda match: match['confidence'])
+
+    def _load_track_hook(self) -> Optional[Callable[[Dict], Any]]:
+        """Loads `on_track(track)` function from user python file.
+
+            Hook returns False to skip the track, dict to override track fields
+            (e.g. {'name': 'fixed name'}) or anything else to keep the track as is.
+        """
+        if not self.track_hook_path:
+            return None
+
+        self.logger.info(f'load track hook: {self.track_hook_path}')
+        return runpy.run_path(self.track_hook_path)['on_track']
+
+    def _load_runs(self) -> Dict[str, Dict]:
+        """Returns history of sync runs keyed by run id."""
+        return self._read_json(self._runs_file, {})
+
+    def _store_run(self, run: Dict):
+        runs = self._load_runs()
+        runs[run['run_id']] = run
+
+        self.logger.info(f'store sync run: {self._runs_file}')
+        self._write_json(self._runs_file, runs)
+
+    def list_runs(self) -> List[Dict]:
+        """Returns finished sync runs, the ones in progress are reported by get_status."""
+        runs = [run for run in self._load_runs().values() if run.get('finished_at')]
+        return sorted(runs, key=lambda run: run['started_at'])
+
+    def get_run_details(self, run_id: str) -> Dict:
+        """Returns sync run with tracks loved and not found by it."""
+        runs = self._load_runs()
+        if run_id not in runs:
+            raise Exception(f'unknown run: {run_id}')
+
+        return {
+            **runs[run_id],
+            'loved': [match for match in self._load_matches().values() if match.get('run_id') == run_id],
+            'not_found': [track for track in self._load_not_found().values() if track.get('run_id') == run_id],
+        }
+
+    def whoami(self) -> Dict[str, str]:
+        """Returns user names authenticated in every Spotify account and target."""
+        users = {
+            f'spotify:{service.account}': service.get_user_name()
+            for service in [self.spotify_service] + self.extra_spotify_services
+        }
+        for target, service in {'lastfm': self.lastfm_service, **self.extra_targets}.items():
+            users[target] = service.get_user_name()
+        return users
+
+    def get_status(self) -> Dict:
+        """Returns configured accounts and targets, synced and unmatched tracks counts, the last finished run
+            and syncs running now. Pending tracks are counted with the latest snapshot if there is one.
+        """
+        targets = ['lastfm', *self.extra_targets]
+        finished = self.list_runs()
+        not_found = self._load_not_found().values()
+
+        return {
+            'spotify_accounts': [service.account for service in [self.spotify_service] + self.extra_spotify_services],
+            'targets': targets,
+            'synced': {target: len(self._load_processed_tracks(target)) for target in targets},
+            'unmatched': {target: len([track for track in not_found if track['target'] == target]) for target in targets},
+            'pending': (
+                {target: len(tracks) for target, tracks in self.plan_sync().items()} if self.list_snapshots() else None
+            ),
+            'last_run': finished[-1] if finished else None,
+            'running': [
+                run['run_id'] for run in self._load_runs().values()
+                if not run.get('finished_at') and is_process_running(run.get('pid'))
+            ],
+            'api_usage': self.api_usage.summarize(self._load_api_usage()),
+        }
+
+    def _load_api_usage(self) -> Dict[str, Dict[str, int]]:
+        return self._read_json(self._api_usage_file, {})
+
+    def _dump_api_usage(self):
+        self._write_json(self._api_usage_file, self.api_usage.merge(self._load_api_usage()))
+
+    def _reauthenticate(self, target: str, service: Any) -> bool:
+        """Renews session of target whose credentials were rejected during sync, returns whether it is renewed."""
+        if not hasattr(service, 'reauthenticate'):
+            self.logger.error(f'{target} credentials are rejected, the rest of tracks is left for the next sync')
+            return False
+
+        self.logger.warning(f'{target} credentials are rejected, re-authenticate')
+        try:
+            service.reauthenticate()
+            return True
+        except Exception as e:
+            self.logger.error(f'{target} re-authentication failed, the rest of tracks is left for the next sync: {e}')
+            return False
+
+    def _should_backdate(self, service: Any, track: Dict) -> bool:
+        """Tells whether track should be scrobbled at its like time before love (BACKDATE_SCROBBLES setting)."""
+        if not self.backdate_scrobbles or not track['added_at'] or not hasattr(service, 'scrobble_track'):
+            return False
+
+        added_at = datetime.fromisoformat(track['added_at'].replace('Z', '+00:00'))
+        return datetime.now(added_at.tzinfo) - added_at < MAX_SCROBBLE_AGE
+
+    def _backdate_scrobble(self, target: str, service: Any, track: Dict, target_track: Any):
+        timestamp = int(datetime.fromisoformat(track['added_at'].replace('Z', '+00:00')).timestamp())
+        self.logger.info(f'scrobble track in {target} at like time {track["added_at"]}: {track}')
+        self._audited(target, 'scrobble', [f'{track["artist"]} - {track["name"]} ({track["id"]})'],
+                      lambda: service.scrobble_track(target_track, timestamp))
+
+    def _run_command_hook(self, name: str, data: Dict):
+        """Runs shell command from HOOKS_* settings with JSON data on stdin."""
+        command = self.hooks.get(name)
+        if not command:
+            return
+
+        self.logger.info(f'run {name} hook: {command}')
+        result = subprocess.run(command, shell=True, input=json.dumps(data), text=True)
+        if result.returncode:
+            self.logger.warning(f'{name} hook exited with code {result.returncode}')
+
+    def sync(self, **options) -> Dict:
+        """Syncs Spotify likes with targets surrounded by pre_sync, post_sync and on_failure command hooks.
+
+            `options` are passed to `sync_spotify_likes_with_lastfm`.
+            Every sync gets run id, which is added to log messages, stored records and the summary.
+        """
+        self._check_writable('sync')
+        options = {**options, 'run_id': str(uuid.uuid4())}
+        run_id_filter = RunIdFilter(options['run_id'])
+        current_run_id.set(options['run_id'])
+        self.logger.addFilter(run_id_filter)
+        started_at = datetime.now()
+        run = {
+            'run_id': options['run_id'],
+            'started_at': started_at.isoformat(),
+            'options': options,
+            # run without finished_at and with alive pid is in progress, see get_status
+            'pid': os.getpid(),
+        }
+        self._store_run(run)
+        self.api_usage.start_run()
+        if self.heartbeat:
+            self.heartbeat.ping('start')
+
+        try:
+            self._run_command_hook('pre_sync', options)
+            try:
+                summary = self.sync_spotify_likes_with_lastfm(**options)
+            except Exception as e:
+                error = redact(str(e))
+                run['error'] = error
+                run['error_kind'] = classify_error(e).value
+                self._run_command_hook('on_failure', {**options, 'error': error})
+                if self.notifier:
+                    self.notifier.notify('Likes sync failed', error, failed=True)
+                if self.heartbeat:
+                    self.heartbeat.ping('fail', error)
+                if self.sentry:
+                    self.sentry.capture_error(e, options['run_id'])
+                raise
+
+            run['summary'] = summary
+            self._run_command_hook('post_sync', summary)
+            if self.notifier:
+                self.notifier.notify('Likes sync finished', format_summary(summary),
+                                     failed=bool(summary['mass_removals']))
+            if self.heartbeat:
+                self.heartbeat.ping(message=format_summary(summary))
+            if self.sentry:
+                self.sentry.report_failures(summary)
+            return summary
+        except BaseException as e:
+            # Ctrl-C and failures outside of sync (e.g. hooks) leave finished run with error too, never without result
+            if 'summary' not in run and 'error' not in run:
+                run['error'] = 'interrupted' if isinstance(e, KeyboardInterrupt) else redact(str(e)) or type(e).__name__
+            raise
+        finally:
+            finished_at = datetime.now()
+            run['finished_at'] = finished_at.isoformat()
+            run['duration_secs'] = (finished_at - started_at).total_seconds()
+            self._store_run(run)
+            self._dump_api_usage()
+            self.logger.removeFilter(run_id_filter)
+            current_track_id.set(None)
+            current_run_id.set(None)
+            # Spotify token cache is created by spotipy with default mode during the first sync
+            self._secure_state_files()
+
+    def sync_spotify_likes_with_lastfm(self, accounts: Optional[List[str]] = None,
+                                       target_names: Optional[List[str]] = None, interactive: bool = False,
+                                       force: bool = False, resync: Optional[List[str]] = None,
+                                       source: str = 'spotify-liked', time_range: str = 'medium',
+                                       slow: bool = False, track_ids: Optional[List[str]] = None,
+                                       artists: Optional[List[str]] = None, order: Optional[str] = None,
+                                       min_confidence: Optional[float] = None, since_days: Optional[int] = None,
+                                       full: bool = False, filters: Optional[Dict[str, Any]] = None,
+                                       run_id: Optional[str] = None) -> Dict:
+        """Loves in LastFM and other configured targets liked tracks of all configured Spotify accounts.
+
+            Every target has own cache, so failure in one target doesn't block others.
+            `accounts` and `target_names` limit the sync to some of configured Spotify accounts and targets.
+            In `interactive` mode not found and low confidence matches are confirmed by user from STDIN.
+            Not found tracks are not searched again for NOT_FOUND_RETRY_DAYS days.
+            `force` ignores local state and loves all tracks again (loves are idempotent),
+            `resync` does the same only for given Spotify track ids.
+            `source` is `spotify-liked` or `spotify-top` - top tracks for short, medium or long `time_range`.
+            `slow` mode makes love calls not more often than once per SLOW_LOVE_INTERVAL_SECS seconds.
+            `track_ids` are fetched from Spotify instead of liked tracks and synced ignoring local state.
+            `artists` limit the sync to tracks of these artists (case insensitive).
+            `order` is `oldest-first` or `newest-first` by like time, so loves keep rough chronological order,
+            tracks are synced in source order by default.
+            Matches with confidence below `min_confidence` (MIN_CONFIDENCE setting by default) are not loved,
+            they are queued for `review approve` or `review reject`.
+            `since_days` (LIKED_SINCE_DAYS setting by default) limits Spotify likes to ones of the last days,
+            the rest of the library is not fetched at all, other sources (e.g. Funkwhale) are fetched fully.
+            `filters` override FILTERS_* settings, e.g. by pipeline.
+            Unchanged Spotify library (see `get_library_state`) is skipped after sync of the whole library
+            unless `force` or `full` is given.
+            Liked tracks with the same artist and normalized song name (remaster, single and album versions)
+            are loved once, the rest are reported in summary `duplicates`.
+            Returns sync summary: counts of liked, skipped, loved, not found and failed per target tracks,
+            durations of sync phases and P50/P95 durations of match and love calls.
+        """
+        all_targets = {'lastfm': self.lastfm_service, **self.extra_targets}
+        # other sources (e.g. Funkwhale) have liked tracks only, they are synced with Spotify accounts
+        all_spotify_services = [self.spotify_service] + self.extra_spotify_services
+        if source == 'spotify-liked' and not track_ids:
+            all_spotify_services += self.extra_sources
+
+        unknown = set(target_names or []) - set(all_targets)
+        unknown |= set(accounts or []) - {service.account for service in all_spotify_services}
+        if unknown:
+            raise Exception(f'unknown or not configured Spotify accounts/targets: {sorted(unknown)}')
+
+        targets = {
+            target: service for target, service in all_targets.items()
+            if target_names is None or target in target_names
+        }
+        spotify_services = [
+            service for service in all_spotify_services
+            if accounts is None or service.account in accounts
+        ]
+        if track_ids:
+            # given tracks don't depend on account, they are fetched once
+            spotify_services = spotify_services[:1]
+        cached_track_ids = {target: set(self._load_processed_tracks(target)) for target in targets}
+        attribution = self._load_attribution()
+        matches = self._load_matches()
+        not_found = self._load_not_found()
+        tombstones = self._load_tombstones()
+        review_queue = self._load_review_queue()
+        library_states = self._load_library_states()
+        library_sizes = self._load_library_sizes()
+        # local state which makes sync of unchanged library do something, e.g. `undo` forgets synced tracks
+        state_fingerprint = {
+            'synced': {target: len(cached_track_ids[target]) for target in sorted(targets)},
+            'tombstones': len(tombstones),
+            # changed filters or hook make other tracks syncable
+            'filters': {**self.filters, **(filters or {})},
+            'track_hook': self.track_hook_path,
+        }
+        new_library_states = {}
+        min_confidence = min_confidence if min_confidence is not None else self.min_confidence
+        filters = {**self.filters, **(filters or {})}
+        since_days = since_days if since_days is not None else self.liked_since_days
+        # only sync of the whole library leaves nothing to do for unchanged one
+        whole_library = not artists and since_days is None and not resync
+        self.matcher_chain.cache = self._load_search_cache()
+        artists_genres = self._load_genres_cache()
+        now = datetime.now()
+        last_love_at = 0.0
+        delays = {target: AdaptiveDelay(self.logger) for target in targets}
+        new_ids = {target: set() for target in targets}
+        reauthentications = {target: 0 for target in targets}
+        auth_failed_targets = set()
+        track_hook = self._load_track_hook()
+        summary = {
+            'run_id': run_id,
+            'liked': 0,
+            'skipped_by_filter': 0,
+            'skipped_by_hook': 0,
+            'skipped_by_user': 0,
+            'skipped_not_found': 0,
+            'skipped_by_tombstone': 0,
+            'skipped_by_budget': 0,
+            'skipped_by_auth': 0,
+            'skipped_in_review': 0,
+            'unchanged_accounts': [],
+            'mass_removals': [],
+            'duplicates': [],
+            'loved': {target: 0 for target in targets},
+            'backdated': {target: 0 for target in targets},
+            'not_found': {target: 0 for target in targets},
+            'queued_for_review': {target: 0 for target in targets},
+            'failures': [],
+            'failed': {target: 0 for target in targets},
+            'phases': {phase: 0.0 for phase in ('fetch', 'filter', 'match', 'love', 'persist')},
+        }
+        # durations of every match and love call, reported as percentiles
+        calls = {'match': [], 'love': []}
+
+        for spotify_service in spotify_services:
+            self.logger.info(f'sync Spotify account: {spotify_service.account}')
+
+            liked_library = source == 'spotify-liked' and not track_ids and isinstance(spotify_service, SpotifyService)
+            if liked_library:
+                library_state = {**spotify_service.get_library_state(), **state_fingerprint}
+                mass_removal = self._find_mass_removal(library_sizes, spotify_service.account, library_state['total'])
+                if mass_removal and self.abort_on_mass_removal:
+                    raise Exception(f'{mass_removal}, sync is aborted (ABORT_ON_MASS_REMOVAL setting)')
+                if mass_removal:
+                    # previous size stays the baseline until the new one is accepted
+                    self.logger.warning(
+                        f'{mass_removal}, check Spotify library and run `accept-library-size` if it is right'
+                    )
+                    summary['mass_removals'].append(mass_removal)
+                else:
+                    self._record_library_size(library_sizes, spotify_service.account, library_state['total'])
+
+            if liked_library and not force and not resync and not full:
+                due_not_found = any(
+                    datetime.fromisoformat(track['retry_after']) <= now
+                    for track in not_found.values() if track['target'] in targets
+                )
+                if library_states.get(spotify_service.account) == library_state and not due_not_found:
+                    self.logger.info(f'nothing to do, Spotify library of {spotify_service.account} is unchanged')
+                    summary['unchanged_accounts'].append(spotify_service.account)
+                    continue
+            if liked_library and whole_library:
+                new_library_states[spotify_service.account] = library_state
+
+            if track_ids:
+                tracks = spotify_service.get_tracks(track_ids)
+            elif source == 'spotify-top':
+                tracks = spotify_service.get_top_tracks(time_range=f'{time_range}_term')
+            elif since_days is not None and isinstance(spotify_service, SpotifyService):
+                tracks = spotify_service.get_recently_liked_tracks(datetime.utcnow() - timedelta(days=since_days))
+            else:
+                tracks = spotify_service.get_liked_tracks()
+
+            # the same recordings are loved once, which of them is kept depends on all tracks and synced ones
+            tracks = list(timed(tracks, summary['phases'], 'fetch'))
+            if order:
+                # unknown like times go first
+                tracks.sort(key=lambda track: track['added_at'] or '', reverse=order == 'newest-first')
+            synced = self._get_synced_tracks(tracks, matches, targets, cached_track_ids, new_ids)
+            duplicates = find_duplicates(tracks, synced)
+
+            for track in tracks:
+                summary['liked'] += 1
+                track_id = track['id']
+                current_track_id.set(track_id)
+                if resync and track_id not in resync:
+                    continue
+                if artists and track['artist'].lower().strip() not in {artist.lower().strip() for artist in artists}:
+                    continue
+                ignore_state = force or bool(resync) or bool(track_ids)
+
+                track_accounts = attribution.setdefault(track_id, [])
+                if spotify_service.account not in track_accounts:
+                    track_accounts.append(spotify_service.account)
+
+                started = time.monotonic()
+                filter_reason = self._filter_track(spotify_service, track, artists_genres, filters)
+                if track_hook and not filter_reason:
+                    hook_result = track_hook(dict(track))
+                summary['phases']['filter'] += time.monotonic() - started
+                if filter_reason:
+                    self.logger.info(f'skip track by filter ({filter_reason}): {track}')
+                    summary['skipped_by_filter'] += 1
+                    continue
+
+                if track_hook:
+                    if hook_result is False:
+                        self.logger.info(f'skip track by hook: {track}')
+                        summary['skipped_by_hook'] += 1
+                        continue
+                    if isinstance(hook_result, dict):
+                        try:
+                            track = Track(**{**track, **hook_result}).dict()
+                        except ValidationError as e:
+                            self.logger.warning(f'skip track with invalid hook changes {hook_result}: {e}')
+                            summary['skipped_by_hook'] += 1
+                            continue
+
+                duplicate_of = duplicates.get(track_id)
+                if duplicate_of:
+                    self.logger.info(f'skip duplicate of {duplicate_of["id"]} track: {track}')
+                    summary['duplicates'].append({
+                        'track_id': track_id,
+                        'artist': track['artist'],
+                        'name': track['name'],
+                        'duplicate_of': duplicate_of['id'],
+                    })
+                    continue
+
+                # relinked track is the same track with new id, state may be kept by any of them
+                same_track_ids = {track_id, track['linked_from']} - {None}
+                for target, service in targets.items():
+                    # tombstones are respected even by force and resync
+                    if any(f'{target}:{id_}' in tombstones for id_ in same_track_ids):
+                        self.logger.debug(f'skip track unloved by user in {target}: {track}')
+                        summary['skipped_by_tombstone'] += 1
+                        continue
+                    if same_track_ids & new_ids[target] or (same_track_ids & cached_track_ids[target] and not ignore_state):
+                        self.logger.debug(f'skip cached/processed track in {target}: {track}')
+                        continue
+
+                    not_found_key = f'{target}:{track_id}'
+                    retry_after = not_found.get(not_found_key, {}).get('retry_after')
+                    if not ignore_state and retry_after and datetime.fromisoformat(retry_after) > now:
+                        self.logger.debug(f'skip not found track in {target} until retry date: {track}')
+                        summary['skipped_not_found'] += 1
+                        continue
+                    if not ignore_state and not_found_key in review_queue:
+                        self.logger.debug(f'skip track waiting for review in {target}: {track}')
+                        summary['skipped_in_review'] += 1
+                        continue
+                    # tracks left after budget is used up stay pending for the next run
+                    if self.api_usage.is_exhausted(target_service(target)):
+                        summary['skipped_by_budget'] += 1
+                        continue
+                    # target with credentials which can't be renewed is not called again, tracks stay pending
+                    if target in auth_failed_targets:
+                        summary['skipped_by_auth'] += 1
+                        continue
+
+                    phase = 'match'
+                    delays[target].wait()
+                    try:
+                        started = time.monotonic()
+                        attempts = []
+                        match = self.matcher_chain.match(service, track, attempts=attempts)
+                        calls['match'].append(time.monotonic() - started)
+                        if interactive and (not match or match.confidence < self.confirm_confidence):
+                            match = self._confirm_match(service, track, match)
+                            if not match:
+                                self.logger.info(f'skip track by user in {target}: {track}')
+                                summary['skipped_by_user'] += 1
+                                continue
+                        if not match:
+                            self.logger.info(f'track is not found in {target}: {track}')
+                            summary['not_found'][target] += 1
+                            not_found[not_found_key] = {
+                                'run_id': run_id,
+                                'target': target,
+                                'track_id': track_id,
+                                'artist': track['artist'],
+                                'name': track['name'],
+                                'retry_after': (now + timedelta(days=self.not_found_retry_days)).isoformat(),
+                                # what every matcher got, see `unmatched list`
+                                'attempts': attempts,
+                            }
+                            continue
+                        if min_confidence is not None and match.confidence < min_confidence:
+                            self.logger.info(
+                                f'queue {match.method} match with confidence {match.confidence:.2f} '
+                                f'for review in {target}: {track}'
+                            )
+                            summary['queued_for_review'][target] += 1
+                            review_queue[not_found_key] = {
+                                'run_id': run_id,
+                                'target': target,
+                                'track_id': track_id,
+                                'artist': track['artist'],
+                                'name': track['name'],
+                                'candidate': str(match.target_track),
+                                'target_track': service.dump_track(match.target_track),
+                                'method': match.method,
+                                'confidence': match.confidence,
+                                'queued_at': now.isoformat(timespec='seconds'),
+                            }
+                            continue
+
+                        if self._should_backdate(service, track):
+                            phase = 'scrobble'
+                            self._backdate_scrobble(target, service, track, match.target_track)
+                            summary['backdated'][target] += 1
+
+                        phase = 'love'
+                        if slow:
+                            time.sleep(max(0.0, last_love_at + self.slow_love_interval_secs - time.monotonic()))
+                            last_love_at = time.monotonic()
 
-        for track in self.spotify_service.get_liked_tracks():
-            track_id = track['id']
-            if track_id in cached_track_ids:
-                self.logger.debug(f'skip cached/processed track: {track}')
+                        self.logger.info(f'set like for track in {target} ({match.method} match): {track}')
+                        started = time.monotonic()
+                        self._audited(target, 'love', [f'{track["artist"]} - {track["name"]} ({track_id})'],
+                                      lambda: service.like_track(match.target_track))
+                        calls['love'].append(time.monotonic() - started)
+                    except BudgetExhaustedError:
+                        # the call wasn't sent, track stays pending like the rest of tracks after budget
+                        summary['skipped_by_budget'] += 1
+                        continue
+                    except Exception as e:
+                        if is_retryable(e):
+                            delays[target].failure()
+                        error = TrackSyncError(track, target, phase, e)
+                        # failures are aggregated into one warning at the end of sync
+                        self.logger.info(str(error))
+                        summary['failed'][target] += 1
+                        summary['failures'].append({
+                            'target': target,
+                            'track_id': track_id,
+                            'artist': track['artist'],
+                            'name': track['name'],
+                            'phase': phase,
+                            'error': str(e),
+                            'kind': classify_error(error).value,
+                        })
+                        # failed track is retried by the next sync, the rest of tracks go on with new session
+                        if classify_error(error) == ErrorKind.AUTH:
+                            reauthentications[target] += 1
+                            too_many = reauthentications[target] > MAX_REAUTHENTICATIONS
+                            if too_many or not self._reauthenticate(target, service):
+                                auth_failed_targets.add(target)
+                        continue
+
+                    delays[target].success()
+                    new_ids[target].add(track_id)
+                    summary['loved'][target] += 1
+                    not_found.pop(not_found_key, None)
+                    review_queue.pop(not_found_key, None)
+                    self._record_match(
+                        matches, target, track_id, track['artist'], track['name'], match.method, match.confidence,
+                        run_id=run_id, target_track=service.dump_track(match.target_track),
+                    )
+
+        started = time.monotonic()
+        self._dump_attribution(attribution)
+        self._dump_not_found(not_found)
+        self._dump_review_queue(review_queue)
+        self._dump_search_cache(self.matcher_chain.cache)
+        if artists_genres:
+            self._dump_genres_cache(artists_genres)
+        if any(new_ids.values()):
+            self._dump_matches(matches)
+
+        for target in targets:
+            if new_ids[target].difference(cached_track_ids[target]):
+                new_cache = cached_track_ids[target].union(new_ids[target])
+                self._dump_processed_tracks(list(new_cache), target)
+            else:
+                self.logger.info(f'all Spotify tracks already synced with {target}')
+
+        # library with pending tracks (failed, left by budget and so on) must be synced again even if it's unchanged
+        pending = (
+            summary['failures'] or summary['skipped_by_budget'] or summary['skipped_by_auth']
+            or summary['skipped_by_user']
+        )
+        if new_library_states and not pending:
+            synced = {target: len(cached_track_ids[target] | new_ids[target]) for target in sorted(targets)}
+            for state in new_library_states.values():
+                state['synced'] = synced
+            self._dump_library_states({**library_states, **new_library_states})
+
+        summary['phases']['persist'] = time.monotonic() - started
+        summary['phases']['match'] = sum(calls['match'])
+        summary['phases']['love'] = sum(calls['love'])
+        summary['calls'] = {
+            name: {'count': len(durations), 'p50': percentile(durations, 50), 'p95': percentile(durations, 95)}
+            for name, durations in calls.items()
+        }
+        summary['api_calls'] = dict(self.api_usage.run_calls)
+        exhausted = [service for service in self.api_usage.max_calls_per_run if self.api_usage.is_exhausted(service)]
+        if exhausted:
+            self.logger.warning(
+                f'calls budget is exhausted for {", ".join(exhausted)}, '
+                f'{summary["skipped_by_budget"]} tracks are left for the next run'
+            )
+
+        summary['failures_by_kind'] = {}
+        for failure in summary['failures']:
+            summary['failures_by_kind'][failure['kind']] = summary['failures_by_kind'].get(failure['kind'], 0) + 1
+
+        review_count = sum(summary['queued_for_review'].values())
+        if review_count:
+            self.logger.info(f'{review_count} low confidence matches are queued, run `review list` to check them')
+
+        not_found_count = sum(summary['not_found'].values())
+        problems = [f'{count} {kind.replace("_", " ")}' for kind, count in summary['failures_by_kind'].items()]
+        if not_found_count:
+            problems.insert(0, f'{not_found_count} not found')
+        if summary['duplicates']:
+            self.logger.info(
+                f'{len(summary["duplicates"])} duplicate liked tracks are loved once, '
+                f'run `dedupe-spotify` to remove them from Spotify'
+            )
+        if problems:
+            self.logger.warning(
+                f'sync problems: {", ".join(problems)}. '
+                f'See `runs show {run_id}`, run `retry-failed` or `retry-unmatched` to sync them again'
+            )
+
+        self.logger.info(f'sync summary: {summary}')
+        return summary
+
+    def undo_run(self, run_id: str) -> List[Dict]:
+        """Unloves tracks loved by the sync run and forgets them in syncer caches."""
+        self._check_writable('undo')
+        targets = {'lastfm': self.lastfm_service, **self.extra_targets}
+        matches = self._load_matches()
+        run_matches = {key: match for key, match in matches.items() if match.get('run_id') == run_id}
+        if not run_matches:
+            raise Exception(f'there are no tracks loved by run {run_id}')
+
+        undone = []
+        for key, match in run_matches.items():
+            service = targets.get(match['target'])
+            if not service:
+                self.logger.warning(f'skip track of not configured target: {match}')
                 continue
 
-            lastfm_track = self.lastfm_service.get_track(track['artist'], track['name'])
-            self.logger.info(f'set like for track: {track}')
+            loved_track = self._load_loved_track(service, match)
+            if loved_track is None:
+                self.logger.warning(f'track is not found in {match["target"]}: {match}')
+                continue
+
+            self.logger.info(f'unlove track in {match["target"]}: {match["artist"]} - {match["name"]}')
+            self._audited(match['target'], 'unlove', [f'{match["artist"]} - {match["name"]}'],
+                          lambda: service.unlike_track(loved_track))
+            undone.append(matches.pop(key))
+
+        for target in {match['target'] for match in undone}:
+            undone_ids = {match['track_id'] for match in undone if match['target'] == target}
+            cached_track_ids = set(self._load_processed_tracks(target))
+            self._dump_processed_tracks(list(cached_track_ids - undone_ids), target)
 
-            self.lastfm_service.like_track(lastfm_track)
-            new_ids.add(track_id)
+        self._dump_matches(matches)
+        return undone
 
-        if new_ids.difference(cached_track_ids):
-            new_cache = cached_track_ids.union(new_ids)
-            self._dump_processed_tracks(list(new_cache))
-        else:
-            self.logger.info('all Spotify tracks already synced with LastFM')
+    def plan_sync(self) -> Dict[str, List[Dict]]:
+        """Returns not synced tracks per target using the latest snapshot, Spotify API is not called."""
+        snapshots = self.list_snapshots()
+        if not snapshots:
+            raise Exception('there are no snapshots, run `snapshot` command first')
+
+        self.logger.info(f'plan sync with snapshot: {snapshots[-1]}')
+        tracks = self.load_snapshot(snapshots[-1])
+        plan = {}
+
+        for target in ['lastfm', *self.extra_targets]:
+            cached_track_ids = set(self._load_processed_tracks(target))
+            plan[target] = [track for track in tracks if track['id'] not in cached_track_ids]
+
+        return plan
+
+    def _confirm_match(self, service, track: Dict, match: Optional[Match]) -> Optional[Match]:
+        """Asks user to love, skip or search again with edited artist and song name, returns match to love."""
+        while True:
+            found = f'{match.target_track} ({match.method} match, {match.confidence:.2f})' if match else 'nothing'
+            message = (f'Spotify track: {track["artist"]} - {track["name"]}, found: {found}. '
+                       f'Answer "l" to love, "e" to edit search terms, anything else to skip: ')
+            answer = input(message).lower().strip()
+
+            if answer == 'l' and match:
+                return match._replace(method='manual')
+            if answer != 'e':
+                return None
+
+            artist = input(f'artist [{track["artist"]}]: ').strip() or track['artist']
+            name = input(f'song name [{track["name"]}]: ').strip() or track['name']
+            match = self.matcher_chain.match(service, {**track, 'artist': artist, 'name': name})
+
+    def run_pipeline(self, name: str) -> Dict:
+        if name not in self.pipelines:
+            raise Exception(f'unknown pipeline: {name}, configured: {sorted(self.pipelines)}')
+
+        pipeline = self.pipelines[name]
+        self.logger.info(f'run pipeline {name}: {pipeline}')
+        return self.sync(accounts=pipeline['sources'], target_names=pipeline['targets'], filters=pipeline['filters'])
 
     def sync_liked_tracks_from_lastfm_with_spotify(self):
         """Gets tracks from last fm liked list and searches them in Spotifyself.
 
             Method sometimes requires user input from STDIN. 
         """
+        self._check_writable('sync')
         tracks_to_load = []
         missed_tracks = []
 
@@ -82,7 +1347,7 @@ class Syncer:
 
         for track in self.lastfm_service.get_liked_tracks(limit=None):
             query = f'{track.track.artist.name} {track.track.get_name()}'
-            results = self.spotify_service.spotify.search(q=query, type='track', limit=50)
+            results = self.spotify_service.search_tracks(query)
 
             sync_track = self._find_search_match(track, results)
             if sync_track and sync_track.spotify_track_uri not in track_uris:
@@ -96,10 +1361,555 @@ class Syncer:
         if tracks_to_load:
             self._add_liked_tracks_to_spotify(tracks_to_load, playlist)
 
+            matches = self._load_matches()
+            for sync_track in tracks_to_load:
+                self._record_match(
+                    matches, 'spotify', sync_track.spotify_track_uri.split(':')[-1], sync_track.last_fm_artist,
+                    sync_track.last_fm_song, sync_track.match_method, sync_track.match_confidence,
+                )
+            self._dump_matches(matches)
+
         self.logger.info(f'store missed tracks: {len(missed_tracks)}')
         if missed_tracks:
             self._store_missed_liked_tracks(missed_tracks)
 
+    def diff_liked_tracks(self) -> Dict[str, List[Tuple[str, str]]]:
+        """Compares Spotify liked tracks with LastFM loved tracks by artist and song name.
+
+            Nothing is changed in both services.
+        """
+        l = fold_name
+
+        spotify_tracks = {(l(track['artist']), l(track['name'])) for track in self.spotify_service.get_liked_tracks()}
+        lastfm_tracks = {
+            (l(track.track.artist.name), l(track.track.get_name()))
+            for track in self.lastfm_service.get_liked_tracks(limit=None)
+        }
+
+        return {
+            'only_spotify': sorted(spotify_tracks - lastfm_tracks),
+            'only_lastfm': sorted(lastfm_tracks - spotify_tracks),
+            'both': sorted(spotify_tracks & lastfm_tracks),
+        }
+
+    def get_playlist_tracks(self, source: str) -> List[Dict]:
+        """Returns Spotify liked (`spotify-liked`) or LastFM loved (`lastfm-loved`) tracks for playlist export,
+            see syncer/playlist.py.
+        """
+        if source == 'spotify-liked':
+            return [
+                {
+                    'artist': track['artist'],
+                    'name': track['name'],
+                    'location': track['url'] or f'https://open.spotify.com/track/{track["id"]}',
+                    'duration_secs': track['duration_ms'] // 1000 if track['duration_ms'] is not None else None,
+                }
+                for track in self.spotify_service.get_liked_tracks()
+            ]
+        if source == 'lastfm-loved':
+            # track url is built by pylast without API calls, loved tracks have no duration
+            return [
+                {
+                    'artist': loved.track.artist.name,
+                    'name': loved.track.get_name(),
+                    'location': loved.track.get_url(),
+                    'duration_secs': None,
+                }
+                for loved in self.lastfm_service.get_liked_tracks(limit=None)
+            ]
+        raise Exception(f'unknown playlist source: {source}')
+
+    def unlove_tracks(self, tracks: List[Tuple[str, str]], keep_skipped: bool = False):
+        """Removes LastFM love from (artist, song name) tracks and forgets them in syncer cache,
+            so wrong loves can be fixed from the syncer itself.
+            With `keep_skipped` tombstones are recorded, so sync never loves the tracks again.
+        """
+        self._check_writable('unlove')
+        l = fold_name
+
+        for artist, name in tracks:
+            lastfm_track = self.lastfm_service.get_track(artist, name)
+            self.logger.info(f'unlove track: {artist} - {name}')
+            self._audited('lastfm', 'unlove', [f'{artist} - {name}'],
+                          lambda: self.lastfm_service.unlike_track(lastfm_track))
+
+        unloved = {(l(artist), l(name)) for artist, name in tracks}
+        liked_tracks = list(self.spotify_service.get_liked_tracks())
+        unloved_ids = {
+            track['id'] for track in liked_tracks
+            if (l(track['artist']), l(track['name'])) in unloved
+        }
+        cached_track_ids = set(self._load_processed_tracks())
+
+        if cached_track_ids & unloved_ids:
+            self._dump_processed_tracks(list(cached_track_ids - unloved_ids))
+
+        if keep_skipped and unloved_ids:
+            tombstones = self._load_tombstones()
+            for track in liked_tracks:
+                if track['id'] in unloved_ids:
+                    tombstones[f'lastfm:{track["id"]}'] = {
+                        'target': 'lastfm',
+                        'track_id': track['id'],
+                        'artist': track['artist'],
+                        'name': track['name'],
+                        'created_at': datetime.now().isoformat(),
+                    }
+            self._dump_tombstones(tombstones)
+
+    def prune_state(self, dry_run: bool = False, force: bool = False, unlove: bool = False) -> Dict[str, int]:
+        """Forgets processed tracks, matches, not found tracks and attribution of tracks which are not liked
+            by any configured Spotify account anymore (or got new ids). Returns removed entries count per state.
+            Only state of liked libraries sync is pruned, tracks loved from other sources (`love-album`, imports,
+            `sync --source spotify-top`, `love-now`) are kept.
+
+            With `unlove` the tracks are unloved in targets too, their matches are kept with prune time,
+            so `restore_pruned` can love them again.
+
+            Spotify library which shrank by more than MASS_REMOVAL_RATIO since the last sync is likely
+            a partial API response, so nothing is pruned then unless `force` is given.
+        """
+        if not dry_run:
+            self._check_writable('prune')
+
+        liked_ids = set()
+        library_sizes = self._load_library_sizes()
+        for service in [self.spotify_service] + self.extra_spotify_services + self.extra_sources:
+            account_ids = set()
+            for track in service.get_liked_tracks():
+                account_ids.add(track['id'])
+                liked_ids |= {track['id'], track['linked_from']} - {None}
+            mass_removal = self._find_mass_removal(library_sizes, service.account, len(account_ids))
+            if mass_removal and not force:
+                raise Exception(f'{mass_removal}, nothing is pruned, use --force if the library is right')
+            if mass_removal:
+                self.logger.warning(mass_removal)
+                if not dry_run:
+                    # forced prune confirms the library is right
+                    self._record_library_size(library_sizes, service.account, len(account_ids))
+        pruned = {}
+        runs = self._load_runs()
+        # loves of other sources (love-album, imports, love-now and so on) are not expected to be liked in Spotify
+        other_source_ids = {
+            match['track_id'] for match in self._load_matches().values()
+            if not self._is_liked_library_record(match, runs)
+        }
+
+        for target in ['lastfm', *self.extra_targets]:
+            if target != 'lastfm' and not os.path.exists(self._get_cache_file(target)):
+                continue
+            track_ids = self._load_processed_tracks(target)
+            kept_ids = [
+                track_id for track_id in track_ids
+                if track_id in liked_ids or track_id in other_source_ids or ':' in track_id
+            ]
+            pruned[f'processed {target}'] = len(track_ids) - len(kept_ids)
+            if pruned[f'processed {target}'] and not dry_run:
+                self._dump_processed_tracks(kept_ids, target)
+
+        if unlove:
+            pruned['unloved'] = len(self._unlove_pruned(liked_ids, runs, dry_run))
+
+        states = (
+            ('matches', self._load_matches, self._dump_matches),
+            ('not found', self._load_not_found, self._dump_not_found),
+        )
+        for name, load, dump in states:
+            records = load()
+            kept = {
+                key: record for key, record in records.items()
+                if record['track_id'] in liked_ids or not self._is_liked_library_record(record, runs)
+            }
+            pruned[name] = len(records) - len(kept)
+            if pruned[name] and not dry_run:
+                dump(kept)
+
+        attribution = self._load_attribution()
+        kept_attribution = {track_id: accounts for track_id, accounts in attribution.items() if track_id in liked_ids}
+        pruned['attribution'] = len(attribution) - len(kept_attribution)
+        if pruned['attribution'] and not dry_run:
+            self._dump_attribution(kept_attribution)
+
+        return pruned
+
+    @staticmethod
+    def _is_liked_library_record(record: Dict, runs: Dict[str, Dict]) -> bool:
+        """Returns whether the match or not found record is made by sync of liked libraries, only such records
+            are pruned when tracks are not liked anymore. Ids of other sources are prefixed, e.g. `mpd:...`.
+        """
+        run = runs.get(record.get('run_id') or '')
+        if ':' in record['track_id'] or not run:
+            return False
+        return run['options'].get('source', 'spotify-liked') == 'spotify-liked' and not run['options'].get('track_ids')
+
+    def _unlove_pruned(self, liked_ids: Set[str], runs: Dict[str, Dict], dry_run: bool) -> List[Dict]:
+        """Unloves tracks loved by liked libraries sync which are not liked anymore and keeps their matches,
+            returns unloved ones.
+        """
+        targets = {'lastfm': self.lastfm_service, **self.extra_targets}
+        matches = [
+            match for match in self._load_matches().values()
+            if match['track_id'] not in liked_ids and self._is_liked_library_record(match, runs)
+        ]
+        if dry_run:
+            return [match for match in matches if match['target'] in targets]
+
+        pruned = self._load_pruned()
+        unloved = []
+        for match in matches:
+            service = targets.get(match['target'])
+            if not service:
+                self.logger.warning(f'skip track of not configured target: {match}')
+                continue
+
+            loved_track = self._load_loved_track(service, match)
+            if loved_track is None:
+                self.logger.warning(f'track is not found in {match["target"]}: {match}')
+                continue
+
+            self.logger.info(f'unlove pruned track in {match["target"]}: {match["artist"]} - {match["name"]}')
+            self._audited(match['target'], 'unlove', [f'{match["artist"]} - {match["name"]} ({match["track_id"]})'],
+                          lambda: service.unlike_track(loved_track))
+            pruned[f'{match["target"]}:{match["track_id"]}'] = {
+                **match, 'pruned_at': datetime.now().isoformat(timespec='seconds'),
+            }
+            unloved.append(match)
+
+        self._dump_pruned(pruned)
+        return unloved
+
+    def list_pruned(self, since: Optional[datetime] = None) -> List[Dict]:
+        return sorted(
+            (
+                match for match in self._load_pruned().values()
+                if not since or datetime.fromisoformat(match['pruned_at']) >= since
+            ),
+            key=lambda match: match['pruned_at'],
+        )
+
+    def restore_pruned(self, since: Optional[datetime] = None, dry_run: bool = False) -> List[Dict]:
+        """Loves again tracks unloved by `prune --unlove` since the time (all of them by default)
+            and restores their synced state, returns restored tracks.
+        """
+        to_restore = self.list_pruned(since)
+        if dry_run:
+            return to_restore
+
+        self._check_writable('restore-pruned')
+        targets = {'lastfm': self.lastfm_service, **self.extra_targets}
+        pruned = self._load_pruned()
+        matches = self._load_matches()
+        restored = []
+        for match in to_restore:
+            service = targets.get(match['target'])
+            if not service:
+                self.logger.warning(f'skip track of not configured target: {match}')
+                continue
+
+            loved_track = self._load_loved_track(service, match)
+            if loved_track is None:
+                self.logger.warning(f'track is not found in {match["target"]}: {match}')
+                continue
+
+            self.logger.info(f'love pruned track in {match["target"]}: {match["artist"]} - {match["name"]}')
+            self._audited(match['target'], 'love', [f'{match["artist"]} - {match["name"]} ({match["track_id"]})'],
+                          lambda: service.like_track(loved_track))
+            pruned.pop(f'{match["target"]}:{match["track_id"]}')
+            matches[f'{match["target"]}:{match["track_id"]}'] = {
+                key: value for key, value in match.items() if key != 'pruned_at'
+            }
+            restored.append(match)
+
+        for target in {match['target'] for match in restored}:
+            cached_track_ids = set(self._load_processed_tracks(target))
+            restored_ids = {match['track_id'] for match in restored if match['target'] == target}
+            self._dump_processed_tracks(list(cached_track_ids | restored_ids), target)
+        if restored:
+            self._dump_matches(matches)
+            self._dump_pruned(pruned)
+        return restored
+
+    def dedupe_spotify_liked_tracks(self, dry_run: bool = False) -> List[Dict]:
+        """Finds Spotify liked tracks with the same ISRC or artist and song name (ignoring remaster, single
+            and album version suffixes) and close durations and removes all but one of them from Spotify library:
+            the synced one or the earliest liked one, the same one sync loves.
+            Live, acoustic and other versions are different recordings, they are kept.
+        """
+        targets = ['lastfm', *self.extra_targets]
+        cached_track_ids = {target: set(self._load_processed_tracks(target)) for target in targets}
+        tracks = list(self.spotify_service.get_liked_tracks())
+        synced = self._get_synced_tracks(tracks, self._load_matches(), targets, cached_track_ids)
+        originals = find_duplicates(tracks, synced)
+        duplicates = [track for track in tracks if track['id'] in originals]
+        for track in duplicates:
+            self.logger.info(f'found duplicate of {originals[track["id"]]["id"]}: {track}')
+
+        if duplicates and not dry_run:
+            self._check_writable('removing duplicates')
+            self.logger.info(f'remove duplicates from Spotify: {len(duplicates)}')
+            duplicate_ids = [track['id'] for track in duplicates]
+            self._audited('spotify', 'unlike', [f'{track["artist"]} - {track["name"]} ({track["id"]})' for track in duplicates],
+                          lambda: self.spotify_service.remove_liked_tracks(duplicate_ids))
+
+        return duplicates
+
+    def love_top_tracks(self, min_plays: Optional[int] = None, like_in_spotify: bool = False) -> List[Tuple[str, str]]:
+        """Loves LastFM tracks played more than `min_plays` times and optionally likes them in Spotify."""
+        self._check_writable('love-top')
+        min_plays = min_plays or self.love_top_min_plays
+        l = fold_name
+
+        loved = {
+            (l(track.track.artist.name), l(track.track.get_name()))
+            for track in self.lastfm_service.get_liked_tracks(limit=None)
+        }
+        newly_loved = []
+
+        for top_item in self.lastfm_service.get_top_tracks():
+            if int(top_item.weight) <= min_plays:
+                # top tracks are ordered by play count
+                break
+
+            track = top_item.item
+            artist, name = track.artist.name, track.get_name()
+            if (l(artist), l(name)) in loved:
+                continue
+
+            self.logger.info(f'love track played {top_item.weight} times: {artist} - {name}')
+            self._audited('lastfm', 'love', [f'{artist} - {name}'], lambda: self.lastfm_service.like_track(track))
+            newly_loved.append((artist, name))
+
+        if like_in_spotify and newly_loved:
+            self._like_in_spotify(newly_loved)
+
+        return newly_loved
+
+    def _search_in_spotify(self, artist: str, name: str) -> Optional[str]:
+        """Returns id of Spotify track with exactly the same artist and song name."""
+        l = fold_name
+        results = self.spotify_service.search_tracks(f'{artist} {name}')
+        found = [
+            item for item in results['tracks']['items']
+            if l(item['name']) == l(name) and l(artist) in {l(a['name']) for a in item['artists']}
+        ]
+        return found[0]['id'] if found else None
+
+    def _like_in_spotify(self, tracks: List[Tuple[str, str]]):
+        track_ids = []
+
+        for artist, name in tracks:
+            track_id = self._search_in_spotify(artist, name)
+            if track_id:
+                track_ids.append(track_id)
+            else:
+                self.logger.info(f'no exact match in Spotify for: {artist} - {name}')
+
+        self.logger.info(f'like tracks in Spotify: {len(track_ids)}')
+        self._audited('spotify', 'like', track_ids, lambda: self.spotify_service.like_tracks(track_ids))
+
+    def love_imported_tracks(self, tracks: List[Dict], dry_run: bool = False) -> List[Dict]:
+        """Loves in LastFM tracks from local libraries ({'id': ..., 'artist': ..., 'name': ..., 'mbid': ...}),
+            ids are prefixed by library name. Loved tracks are remembered like synced Spotify tracks.
+            Returns tracks with found LastFM match (or error).
+        """
+        if not dry_run:
+            self._check_writable('import')
+
+        cached_track_ids = set(self._load_processed_tracks())
+        matches = self._load_matches()
+        results = []
+
+        for track in tracks:
+            if track['id'] in cached_track_ids:
+                continue
+
+            match = self.matcher_chain.match(self.lastfm_service, track)
+            result = {**track, 'match': str(match.target_track) if match else None, 'method': match and match.method}
+            results.append(result)
+            if not match or dry_run:
+                continue
+
+            self.logger.info(f'set like for track in lastfm ({match.method} match): {track}')
+            self._audited('lastfm', 'love', [f'{track["artist"]} - {track["name"]}'],
+                          lambda: self.lastfm_service.like_track(match.target_track))
+            cached_track_ids.add(track['id'])
+            self._record_match(
+                matches, 'lastfm', track['id'], track['artist'], track['name'], match.method, match.confidence,
+                target_track=self.lastfm_service.dump_track(match.target_track),
+            )
+
+        if not dry_run and any(result['match'] for result in results):
+            self._dump_matches(matches)
+            self._dump_processed_tracks(list(cached_track_ids))
+            self._dump_search_cache(self.matcher_chain.cache)
+        return results
+
+    def love_now(self) -> Optional[Dict]:
+        """Loves in LastFM the song playing in MPD and records the match, returns the song or None if nothing plays."""
+        self._check_writable('love-now')
+        song = self.mpd_service.get_current_song()
+        if not song:
+            return None
+
+        match = self.matcher_chain.match(self.lastfm_service, song)
+        if not match:
+            raise Exception(f'track is not found in LastFM: {song["artist"]} - {song["name"]}')
+
+        self.logger.info(f'set like for track in lastfm ({match.method} match): {song}')
+        self._audited('lastfm', 'love', [f'{song["artist"]} - {song["name"]}'],
+                      lambda: self.lastfm_service.like_track(match.target_track))
+
+        matches = self._load_matches()
+        track_id = f'mpd:{song["mbid"] or song["file"]}'
+        self._record_match(
+            matches, 'lastfm', track_id, song['artist'], song['name'], match.method, match.confidence,
+            target_track=self.lastfm_service.dump_track(match.target_track),
+        )
+        self._dump_matches(matches)
+        return song
+
+    def sync_lastfm_accounts(self, target_names: Optional[List[str]] = None,
+                             dry_run: bool = False) -> Dict[str, List[Tuple[str, str]]]:
+        """Loves tracks loved in the main LastFM account in extra LastFM accounts (`lastfm-<name>` targets).
+            Loves of every target are fetched, so no local state is needed. Returns newly loved tracks per target.
+        """
+        if not dry_run:
+            self._check_writable('sync-lastfm')
+
+        targets = {
+            target: service for target, service in self.extra_targets.items()
+            if target.startswith('lastfm-') and (target_names is None or target in target_names)
+        }
+        if not targets:
+            raise Exception('there are no extra LastFM accounts, see LASTFM_EXTRA_ACCOUNTS setting')
+
+        l = fold_name
+        source_tracks = [
+            (track.track.artist.name, track.track.get_name())
+            for track in self.lastfm_service.get_liked_tracks(limit=None)
+        ]
+        loved = {}
+
+        for target, service in targets.items():
+            target_loved = {
+                (l(track.track.artist.name), l(track.track.get_name())) for track in service.get_liked_tracks(limit=None)
+            }
+            loved[target] = [(artist, name) for artist, name in source_tracks if (l(artist), l(name)) not in target_loved]
+
+            for artist, name in loved[target]:
+                if dry_run:
+                    continue
+                self.logger.info(f'set like for track in {target}: {artist} - {name}')
+                self._audited(target, 'love', [f'{artist} - {name}'],
+                              lambda: service.like_track(service.load_track({'artist': artist, 'name': name})))
+
+        return loved
+
+    def import_loved_tracks(self, tracks: List[Tuple[str, str]], dry_run: bool = False) -> List[Dict]:
+        """Likes in Spotify (artist, song name) tracks loved in LastFM, e.g. from LastFM data export,
+            and marks them as synced with LastFM, so sync doesn't love them again. Returns found tracks.
+        """
+        if not dry_run:
+            self._check_writable('import')
+
+        liked_ids = {track['id'] for track in self.spotify_service.get_liked_tracks()}
+        found = []
+        for artist, name in tracks:
+            track_id = self._search_in_spotify(artist, name)
+            if not track_id:
+                self.logger.info(f'no exact match in Spotify for: {artist} - {name}')
+                continue
+            found.append({'artist': artist, 'name': name, 'spotify_id': track_id, 'liked': track_id in liked_ids})
+
+        if dry_run:
+            return found
+
+        new_ids = [track['spotify_id'] for track in found if not track['liked']]
+        if new_ids:
+            self.logger.info(f'like tracks in Spotify: {len(new_ids)}')
+            self._audited('spotify', 'like', new_ids, lambda: self.spotify_service.like_tracks(new_ids))
+
+        cached_track_ids = set(self._load_processed_tracks())
+        found_ids = {track['spotify_id'] for track in found}
+        if found_ids - cached_track_ids:
+            self._dump_processed_tracks(list(cached_track_ids | found_ids))
+        return found
+
+    def import_listenbrainz_loves(self, dry_run: bool = False) -> List[Dict]:
+        """Likes in Spotify recordings loved in ListenBrainz, they are found by ISRCs of the MusicBrainz recording
+            and by artist and song name when ISRCs are unknown to Spotify. Returns newly liked tracks.
+        """
+        if 'listenbrainz' not in self.extra_targets:
+            raise Exception('ListenBrainz is not configured, set LISTENBRAINZ_TOKEN')
+        if not dry_run:
+            self._check_writable('import')
+
+        listenbrainz = self.extra_targets['listenbrainz']
+        musicbrainz = MusicBrainzService(self.logger)
+        liked_ids = {track['id'] for track in self.spotify_service.get_liked_tracks()}
+        imported = []
+
+        for recording in listenbrainz.get_loved_recordings():
+            track_id, method = None, None
+            for isrc in musicbrainz.get_isrcs(recording['mbid']):
+                found = self.spotify_service.find_track_by_isrc(isrc)
+                if found:
+                    track_id, method = found['id'], 'isrc'
+                    break
+            if not track_id and recording['artist'] and recording['name']:
+                track_id, method = self._search_in_spotify(recording['artist'], recording['name']), 'search'
+
+            if not track_id:
+                self.logger.info(f'ListenBrainz recording is not found in Spotify: {recording}')
+                continue
+            if track_id in liked_ids:
+                continue
+
+            liked_ids.add(track_id)
+            imported.append({**recording, 'spotify_id': track_id, 'method': method})
+
+        if imported and not dry_run:
+            self.logger.info(f'like tracks in Spotify: {len(imported)}')
+            track_ids = [track['spotify_id'] for track in imported]
+            self._audited('spotify', 'like', track_ids, lambda: self.spotify_service.like_tracks(track_ids))
+        return imported
+
+    def create_snapshot(self) -> str:
+        """Saves all Spotify liked tracks into timestamped snapshot file, returns snapshot name."""
+        name = datetime.now().strftime('%Y-%m-%dT%H-%M-%S')
+        tracks = list(self.spotify_service.get_liked_tracks())
+
+        os.makedirs(self._snapshots_dir, exist_ok=True)
+        self._write_json(os.path.join(self._snapshots_dir, f'{name}.json'), tracks)
+
+        self.logger.info(f'saved snapshot {name} with {len(tracks)} tracks')
+        return name
+
+    def list_snapshots(self) -> List[str]:
+        if not os.path.exists(self._snapshots_dir):
+            return []
+        return sorted(file[:-len('.json')] for file in os.listdir(self._snapshots_dir) if file.endswith('.json'))
+
+    def load_snapshot(self, name: str) -> List[Dict]:
+        with open(os.path.join(self._snapshots_dir, f'{name}.json')) as f:
+            return json.load(f)
+
+    def diff_snapshots(self, old: str, new: str) -> Dict[str, List[Dict]]:
+        old_tracks = {track['id']: track for track in self.load_snapshot(old)}
+        new_tracks = {track['id']: track for track in self.load_snapshot(new)}
+
+        return {
+            'added': [track for track_id, track in new_tracks.items() if track_id not in old_tracks],
+            'removed': [track for track_id, track in old_tracks.items() if track_id not in new_tracks],
+        }
+
+    def get_unavailable_liked_tracks(self) -> List[Dict]:
+        """Returns Spotify liked tracks which are not playable (greyed out) in user's market."""
+        return [
+            track for track in self.spotify_service.get_liked_tracks(market=self.spotify_service.market or 'from_token')
+            if not track['is_playable']
+        ]
+
     def _find_search_match(self, track: pylast.LovedTrack, search_results, match_ratio: int = 85) -> Optional[SyncTrack]:
         
         if not search_results['tracks']['total']:
@@ -116,23 +1926,26 @@ class Syncer:
         for search_item in search_results['tracks']['items']:
             for artist in search_item['artists']:
                 spotify_artist, spotify_song_name = l(artist['name']), l(search_item['name'])
-                sync_track = SyncTrack(
+                sync_track = lambda method, confidence: SyncTrack(
                     last_fm_artist=lf_artist,
                     last_fm_song=lf_song_name,
                     spotify_track_uri=search_item['uri'],
+                    match_method=method,
+                    match_confidence=confidence,
                 )
                 
                 artist_ratio = fuzz.ratio(lf_artist, spotify_artist)
                 song_ratio = fuzz.ratio(lf_song_name, spotify_song_name)
 
                 if song_ratio >= match_ratio and artist_ratio >= match_ratio:
-                    return sync_track
+                    method = 'exact' if song_ratio == artist_ratio == 100 else 'fuzzy'
+                    return sync_track(method, min(song_ratio, artist_ratio) / 100)
 
                 # (spotify_artist, spotify_song_name),  (lf_artist, lf_song_name), artist_ratio, song_ratio
                 if song_ratio + artist_ratio >= 140:
                     yes = are_tracks_the_same((spotify_artist, spotify_song_name), (lf_artist, lf_song_name))
                     if yes:
-                        return sync_track
+                        return sync_track('manual', (song_ratio + artist_ratio) / 200)
                     else:
                         self.logger.info('answer is "no"')
                 
@@ -152,14 +1965,63 @@ class Syncer:
             pickle.dump(missed_tracks, file)
 
 
-if __name__ == "__main__":
-    import sys
+def timed(items: Iterable, phases: Dict[str, float], phase: str) -> Generator:
+    """Yields items adding time spent on getting every item (e.g. API pagination) to the phase duration."""
+    items = iter(items)
+    while True:
+        started = time.monotonic()
+        item = next(items, None)
+        phases[phase] += time.monotonic() - started
+        if item is None:
+            return
+        yield item
+
+
+def is_process_running(pid: Optional[int]) -> bool:
+    if not pid:
+        return False
+
+    try:
+        # signal 0 only checks that process exists
+        os.kill(pid, 0)
+    except ProcessLookupError:
+        return False
+    except PermissionError:
+        return True
+    return True
+
+
+def percentile(values: List[float], percent: int) -> float:
+    if not values:
+        return 0.0
+
+    values = sorted(values)
+    return values[min(len(values) - 1, len(values) * percent // 100)]
+
+
+def init_container(read_only: bool = False, settings: Optional[Settings] = None,
+                   log_file: Optional[str] = None) -> DIContainer:
+    """Creates DI container with settings (from environment by default) and wires Syncer to it.
+
+        Library usage: `container = init_container()`, then `Syncer().sync()`
+        and `container.shutdown_resources()` when done.
+    """
+    settings = settings or Settings()
+    add_secrets(*find_secrets(settings.dict()))
+    # services are created lazily, so they get the User-Agent
+    set_user_agent(settings.user_agent, append_host=settings.user_agent_append_host)
     container = DIContainer()
     container.init_resources()
-    container.config.from_pydantic(Settings())
+    container.config.from_pydantic(settings)
+    if read_only:
+        container.config.read_only.override(True)
+    if log_file:
+        container.config.log_file.override(log_file)
     container.wire(modules=[sys.modules[__name__]])
+    return container
 
-    Syncer().sync_spotify_likes_with_lastfm()
-    # Syncer().sync_liked_tracks_from_lastfm_with_spotify()
-    
-    container.shutdown_resources()
+
+if __name__ == "__main__":
+    # kept for `python syncer/app.py` invocations, see syncer/cli.py
+    from syncer.cli import main
+    main()
@@ -0,0 +1,53 @@
+"""Local performance checks of matching and cache operations, no API calls are made."""
+
+import json
+import os
+import tempfile
+import time
+from typing import Callable, Dict, List
+
+from fuzzywuzzy import fuzz
+
+
+def measure(func: Callable, repeat: int = 5) -> float:
+    """Returns the best of `repeat` runs in seconds."""
+    timings = []
+    for _ in range(repeat):
+        started = time.perf_counter()
+        func()
+        timings.append(time.perf_counter() - started)
+    return min(timings)
+
+
+def bench_matching(tracks: List[Dict], normalize: Callable[[str], str]) -> Dict[str, float]:
+    def match():
+        # every track is compared with its neighbour like search results are compared with LastFM track
+        for track, candidate in zip(tracks, tracks[1:] + tracks[:1]):
+            fuzz.ratio(normalize(track['name']), normalize(candidate['name']))
+            fuzz.ratio(track['artist'].lower(), candidate['artist'].lower())
+
+    seconds = measure(match)
+    return {'tracks': len(tracks), 'seconds': seconds, 'tracks_per_second': len(tracks) / seconds if seconds else 0}
+
+
+def bench_cache(tracks: List[Dict]) -> Dict[str, float]:
+    track_ids = [track['id'] for track in tracks]
+    cached_ids = set(track_ids[::2])
+
+    with tempfile.TemporaryDirectory() as tmp_dir:
+        cache_file = os.path.join(tmp_dir, 'cache')
+
+        def dump():
+            with open(cache_file, 'w') as f:
+                json.dump(track_ids, f)
+
+        def load():
+            with open(cache_file) as f:
+                json.load(f)
+
+        return {
+            'ids': len(track_ids),
+            'dump_seconds': measure(dump),
+            'load_seconds': measure(load),
+            'not_synced_lookup_seconds': measure(lambda: [i for i in track_ids if i not in cached_ids]),
+        }
@@ -0,0 +1,626 @@
+"""Command line interface, Syncer and other modules are usable as a library without it, see syncer/app.py."""
+
+import argparse
+import csv
+import json
+import plistlib
+import re
+import subprocess
+import sys
+import traceback
+from datetime import datetime
+from typing import Dict, List, Optional, Tuple
+
+from services.errors import EXIT_CODES, ErrorKind, classify_error
+from syncer.app import Syncer, init_container
+from syncer.bench import bench_cache, bench_matching
+from syncer.config import get_setting, set_setting
+from syncer.logs import redact
+from syncer.matching import normalize_song_name
+from syncer.playlist import PLAYLIST_FORMATS, write_playlist
+from syncer.report import REPORT_FORMATS, lastfm_search_url, write_report
+from syncer.server import SyncerServer, next_scheduled_run
+from syncer.systemd import USER_UNITS_DIR, UNIT_NAME, install_units, render_units
+
+SERVE_LOG_FILE = 'syncer.log'
+
+
+def print_diff(diff: Dict[str, List[Tuple[str, str]]]):
+    sections = (
+        ('only in Spotify', 'only_spotify'),
+        ('only in LastFM', 'only_lastfm'),
+        ('in both', 'both'),
+    )
+    for title, key in sections:
+        print(f'{title}: {len(diff[key])}')
+        for artist, name in diff[key]:
+            print(f'    {artist} - {name}')
+
+
+def print_tracks_table(tracks: List[Dict], columns: Tuple[str, ...]):
+    widths = [max([len(column)] + [len(str(track[column])) for track in tracks]) for column in columns]
+    print('  '.join(column.ljust(width) for column, width in zip(columns, widths)))
+    for track in tracks:
+        print('  '.join(str(track[column]).ljust(width) for column, width in zip(columns, widths)))
+
+
+def print_match_test(result: Dict):
+    print(f'rewritten: {result["rewritten"]}')
+    for strategy in result['strategies']:
+        found = f'{strategy["found"]} ({strategy["confidence"]:.2f})' if strategy['found'] else 'nothing'
+        error = f', error: {strategy["error"]}' if strategy['error'] else ''
+        print(f'{strategy["matcher"]}: {found}{error}')
+        for candidate in strategy['candidates']:
+            print(f'    {candidate["track"]}: artist {candidate["artist_ratio"]}, song {candidate["song_ratio"]}')
+
+    decision = result['decision']
+    print(f'decision: {decision["found"]} by {decision["matcher"]}' if decision else 'decision: not found')
+
+
+def get_next_runs(syncer: Syncer, schedule: Optional[str]) -> Dict[str, datetime]:
+    """Returns next scheduled runs of `serve` by SCHEDULE and pipeline schedules."""
+    next_runs = {'sync': next_scheduled_run(syncer, schedule)} if schedule else {}
+    for name, pipeline in syncer.pipelines.items():
+        if pipeline['schedule']:
+            next_runs[f'pipeline {name}'] = next_scheduled_run(syncer, pipeline['schedule'], name)
+    return next_runs
+
+
+def print_status(status: Dict, next_runs: Dict[str, datetime]):
+    print(f'Spotify accounts: {", ".join(status["spotify_accounts"])}')
+    for target in status['targets']:
+        pending = status['pending'][target] if status['pending'] is not None else 'unknown (no snapshots)'
+        print(f'{target}: synced {status["synced"][target]}, unmatched {status["unmatched"][target]}, pending {pending}')
+
+    last_run = status['last_run']
+    if last_run:
+        print(f'last run: {last_run["run_id"]} at {last_run["started_at"]}, {format_run_result(last_run)}')
+    else:
+        print('last run: never')
+    print(f'running now: {", ".join(status["running"]) or "no"}')
+    if not next_runs:
+        print('next scheduled run: none, SCHEDULE is not set')
+    for name, next_run in next_runs.items():
+        # missed runs are caught up once `serve` is started
+        overdue = ', overdue' if next_run < datetime.now() else ''
+        print(f'next scheduled {name}: {next_run.isoformat(sep=" ", timespec="minutes")}{overdue}')
+    for service, usage in status['api_usage'].items():
+        budget = f', per run budget {usage["max_calls_per_run"]}' if usage['max_calls_per_run'] else ''
+        print(f'{service} API calls: {usage["last_hour"]} in the last hour, {usage["last_day"]} in the last day{budget}')
+
+
+def format_phases(phases: Dict[str, float]) -> str:
+    return ' '.join(f'{phase} {seconds:.1f}s' for phase, seconds in phases.items())
+
+
+def format_run_result(run: Dict) -> str:
+    """Returns error or loved tracks counts of finished run, interrupted runs stored by older versions have none."""
+    if run.get('error'):
+        return f'failed: {run["error"]}'
+    if 'summary' not in run:
+        return 'interrupted'
+    return f'loved: {run["summary"]["loved"]}'
+
+
+def print_runs(runs: List[Dict]):
+    for run in runs:
+        phases = format_phases(run.get('summary', {}).get('phases', {}))
+        print(f'{run["run_id"]}  {run["started_at"]}  {run["duration_secs"]:.1f}s  {format_run_result(run)}  {phases}')
+
+
+def print_run(run: Dict):
+    print(f'run: {run["run_id"]}')
+    if not run.get('finished_at'):
+        print(f'started: {run["started_at"]}, still running or interrupted')
+    else:
+        print(f'started: {run["started_at"]}, finished: {run["finished_at"]}, duration: {run["duration_secs"]:.1f}s')
+    if run.get('error'):
+        print(f'error: {run["error"]}')
+
+    summary = run.get('summary', {})
+    for phase, seconds in summary.get('phases', {}).items():
+        print(f'    {phase}: {seconds:.1f}s')
+    for name, stats in summary.get('calls', {}).items():
+        print(f'    {name} calls: {stats["count"]}, p50: {stats["p50"]:.3f}s, p95: {stats["p95"]:.3f}s')
+
+    sections = (
+        ('loved', run['loved']),
+        ('failed', summary.get('failures', [])),
+        ('not found', run['not_found']),
+    )
+    for title, tracks in sections:
+        print(f'{title}: {len(tracks)}')
+        for track in tracks:
+            error = f': {track["phase"]} failed: {track["error"]}' if track.get('error') else ''
+            print(f'    [{track["target"]}] {track["artist"]} - {track["name"]} ({track["track_id"]}){error}')
+
+
+def parse_track_id(value: str) -> str:
+    """Returns Spotify track id from id, `spotify:track:<id>` uri or https://open.spotify.com/track/<id> url."""
+    found = re.search(r'([0-9A-Za-z]{22})(?:\?.*)?$', value.strip())
+    if not found:
+        raise Exception(f'wrong Spotify track id or url: {value}')
+    return found.group(1)
+
+
+def read_track_ids_file(path: str) -> List[str]:
+    """Reads file with Spotify track id or url per line, empty lines and # comments are skipped."""
+    with open(path) as f:
+        return [parse_track_id(line) for line in f if line.strip() and not line.strip().startswith('#')]
+
+
+def read_loved_export(path: str) -> List[Tuple[str, str]]:
+    """Reads LastFM loved tracks export: JSON with `lovedtracks` API pages (lastfm-backup) or list of
+        {"artist": ..., "name": ...} objects, CSV with `artist` and `track`/`title`/`name` header columns
+        or `artist,song name` rows without header.
+    """
+    if path.endswith('.json'):
+        with open(path) as f:
+            data = json.load(f)
+
+        items = data if isinstance(data, list) else [data]
+        tracks = []
+        for item in items:
+            # API page has tracks inside, plain list item is a track itself
+            for track in item['lovedtracks']['track'] if 'lovedtracks' in item else [item]:
+                artist = track['artist']
+                if isinstance(artist, dict):
+                    artist = artist.get('name') or artist.get('#text')
+                tracks.append((artist, track['name']))
+        return tracks
+
+    with open(path, newline='') as f:
+        rows = [row for row in csv.reader(f) if row]
+    header = [column.lower().strip() for column in rows[0]] if rows else []
+    name_column = next((column for column in ('track', 'title', 'name') if column in header), None)
+    if 'artist' not in header or not name_column:
+        return [(row[0], row[1]) for row in rows]
+
+    artist_index, name_index = header.index('artist'), header.index(name_column)
+    return [(row[artist_index], row[name_index]) for row in rows[1:]]
+
+
+def read_itunes_library(path: str, min_rating: int) -> List[Dict]:
+    """Returns loved tracks and tracks rated at least `min_rating` stars from iTunes/Music.app Library.xml."""
+    with open(path, 'rb') as f:
+        library = plistlib.load(f)
+
+    return [
+        {'id': f'itunes:{track["Persistent ID"]}', 'artist': track['Artist'], 'name': track['Name']}
+        for track in library.get('Tracks', {}).values()
+        # rating is 0-100, 20 per star, computed rating comes from album rating, not from the track one
+        if track.get('Artist') and track.get('Name')
+        and (track.get('Loved') or (track.get('Rating', 0) >= min_rating * 20 and not track.get('Rating Computed')))
+    ]
+
+
+def read_beets_library(query: List[str]) -> List[Dict]:
+    """Returns tracks of beets library matching beets query, e.g. `rating:5`, with their MusicBrainz ids."""
+    # beets CLI parses its own query syntax and knows where the library database is
+    output = subprocess.run(
+        ['beet', 'ls', '-f', '$id\t$artist\t$title\t$mb_trackid\t$path', *query],
+        check=True, capture_output=True, text=True,
+    ).stdout
+
+    tracks = []
+    for line in output.splitlines():
+        item_id, artist, name, mbid, path = line.split('\t')
+        tracks.append({
+            'id': f'beets:{mbid or item_id}', 'artist': artist, 'name': name, 'mbid': mbid or None, 'path': path,
+        })
+    return tracks
+
+
+def write_unmatched_csv(path: str, tracks: List[Dict]):
+    """Writes not found tracks with LastFM search links, e.g. for fixing them in a spreadsheet."""
+    with open(path, 'w', newline='') as f:
+        writer = csv.writer(f)
+        writer.writerow(['target', 'artist', 'name', 'track_id', 'retry_after', 'attempts', 'lastfm_search'])
+        for track in tracks:
+            attempts = '; '.join(f'{attempt["matcher"]}: {attempt["result"]}' for attempt in track['attempts'])
+            writer.writerow([
+                track['target'], track['artist'], track['name'], track['track_id'], track['retry_after'], attempts,
+                lastfm_search_url(track['artist'], track['name']),
+            ])
+
+
+def read_tracks_file(path: str) -> List[Tuple[str, str]]:
+    """Reads CSV file with `artist,song name` rows."""
+    with open(path, newline='') as f:
+        return [(row[0], row[1]) for row in csv.reader(f) if row]
+
+
+def print_redacted_exception(exc_type, exc, tb):
+    print(redact(''.join(traceback.format_exception(exc_type, exc, tb))), file=sys.stderr, end='')
+
+
+def main():
+    # errors of third-party clients embed request urls with keys
+    sys.excepthook = print_redacted_exception
+    parser = argparse.ArgumentParser(description='Syncs liked tracks between Spotify and LastFM')
+    parser.add_argument('--read-only', action='store_true',
+                        help='forbid changing syncer state and liked tracks, e.g. for `diff` or `query` during sync')
+    parser.add_argument('--log-file', help='also write logs to this file, rotated at 10MB (LOG_FILE setting), '
+                                           f'`serve` writes to {SERVE_LOG_FILE} by default')
+    subparsers = parser.add_subparsers(dest='command')
+    sync_parser = subparsers.add_parser('sync', help='love LastFM tracks which are liked in Spotify (default)')
+    sync_parser.add_argument('--plan', action='store_true',
+                             help='only show not synced tracks from the latest snapshot, nothing is fetched or changed')
+    sync_parser.add_argument('--interactive', action='store_true',
+                             help='confirm not found and low confidence matches (CONFIRM_CONFIDENCE setting)')
+    sync_parser.add_argument('--force', action='store_true', help='ignore local state and love all tracks again')
+    sync_parser.add_argument('--resync', action='append', metavar='TRACK_ID',
+                             help='love again only this Spotify track, can be repeated')
+    sync_parser.add_argument('--slow', action='store_true',
+                             help='spread love calls evenly, one per SLOW_LOVE_INTERVAL_SECS seconds')
+    sync_parser.add_argument('--track', action='append', metavar='TRACK_ID_OR_URL',
+                             help='sync only this Spotify track ignoring local state, can be repeated')
+    sync_parser.add_argument('--from-file', help='file with Spotify track id or url per line, same as --track')
+    sync_parser.add_argument('--artist', action='append',
+                             help='sync only tracks of this artist, can be repeated')
+    sync_parser.add_argument('--source', choices=('spotify-liked', 'spotify-top'), default='spotify-liked')
+    sync_parser.add_argument('--range', choices=('short', 'medium', 'long'), default='medium',
+                             help='time range of spotify-top source')
+    sync_parser.add_argument('--order', choices=('oldest-first', 'newest-first'),
+                             help='sync tracks in order of like time, e.g. to love old likes first')
+    sync_parser.add_argument('--min-confidence', type=float, metavar='CONFIDENCE',
+                             help='queue matches with lower confidence for `review` instead of loving them, '
+                                  'e.g. 0.9 for fuzzy ones (MIN_CONFIDENCE setting)')
+    sync_parser.add_argument('--since-days', type=int, metavar='DAYS',
+                             help='fetch only Spotify likes of the last DAYS days (LIKED_SINCE_DAYS setting)')
+    sync_parser.add_argument('--report', metavar='FILE',
+                             help='write run report with not found tracks and failures, FILE is .md, .json or .html')
+    subparsers.add_parser('status', help='show accounts, synced/unmatched/pending counts, last and running syncs')
+    subparsers.add_parser('whoami', help='show user names authenticated in Spotify accounts and targets')
+    subparsers.add_parser('diff', help='show liked tracks difference between Spotify and LastFM, changes nothing')
+    unlove_parser = subparsers.add_parser('unlove', help='remove LastFM love from tracks')
+    unlove_parser.add_argument('--artist')
+    unlove_parser.add_argument('--track')
+    unlove_parser.add_argument('--from-file', help='CSV file with `artist,song name` rows')
+    unlove_parser.add_argument('--keep-skipped', action='store_true',
+                               help='never love these tracks again, even with `sync --force`')
+    tombstones_parser = subparsers.add_parser('tombstones', help='show tracks which sync never loves again (default)')
+    tombstones_subparsers = tombstones_parser.add_subparsers(dest='tombstones_command')
+    tombstones_subparsers.add_parser('list', help='show tombstones (default)')
+    tombstones_remove_parser = tombstones_subparsers.add_parser('remove', help='allow syncing tracks again')
+    tombstones_remove_parser.add_argument('track_id', nargs='+')
+    review_parser = subparsers.add_parser('review', help='show, love or reject matches queued by --min-confidence')
+    review_parser.add_argument('--target', help='only matches in this target')
+    review_subparsers = review_parser.add_subparsers(dest='review_command')
+    review_subparsers.add_parser('list', help='show queued matches, the least confident first (default)')
+    for name, help_ in (('approve', 'love queued matches'), ('reject', 'drop queued matches, tracks become unmatched')):
+        review_action_parser = review_subparsers.add_parser(name, help=help_)
+        review_action_parser.add_argument('track_id', nargs='*')
+        review_action_parser.add_argument('--all', action='store_true', help='all queued matches')
+    dedupe_parser = subparsers.add_parser('dedupe-spotify', help='remove duplicate Spotify liked tracks')
+    dedupe_parser.add_argument('--dry-run', action='store_true', help='only show duplicates')
+    love_album_parser = subparsers.add_parser('love-album', help='love all tracks of the album found in Spotify')
+    love_album_parser.add_argument('--artist', required=True)
+    love_album_parser.add_argument('--album', required=True)
+    love_album_parser.add_argument('--target', action='append', help='love only in this target, all by default')
+    subparsers.add_parser('love-now', help='love in LastFM the song playing in MPD/Mopidy (MPD_* settings)')
+    sync_lastfm_parser = subparsers.add_parser('sync-lastfm', help='love main LastFM account loves in extra accounts')
+    sync_lastfm_parser.add_argument('--target', action='append', help='lastfm-<name> target, all by default')
+    sync_lastfm_parser.add_argument('--dry-run', action='store_true', help='only show tracks which would be loved')
+    import_loved_parser = subparsers.add_parser('import-loved',
+                                                help='like in Spotify tracks from LastFM loved tracks export')
+    import_loved_parser.add_argument('file', help='CSV or JSON (lastfm-backup) export')
+    import_loved_parser.add_argument('--dry-run', action='store_true', help='only show found Spotify tracks')
+    import_itunes_parser = subparsers.add_parser('import-itunes',
+                                                 help='love in LastFM loved and high rated iTunes/Music.app tracks')
+    import_itunes_parser.add_argument('library', help='Library.xml exported from iTunes or Music.app')
+    import_itunes_parser.add_argument('--min-rating', type=int, default=4, help='stars, default 4')
+    import_itunes_parser.add_argument('--dry-run', action='store_true', help='only show found LastFM tracks')
+    export_playlist_parser = subparsers.add_parser('export-playlist',
+                                                   help='write liked or loved tracks to playlist file')
+    export_playlist_parser.add_argument('--source', choices=('spotify-liked', 'lastfm-loved', 'beets'),
+                                        default='spotify-liked', help='beets source has local file paths')
+    export_playlist_parser.add_argument('--format', choices=PLAYLIST_FORMATS, default='m3u')
+    export_playlist_parser.add_argument('--output', help='playlist file, default <source>.<format>')
+    export_playlist_parser.add_argument('--query', nargs='*', default=[],
+                                        help='beets query for beets source, e.g. rating:5')
+    import_beets_parser = subparsers.add_parser('import-beets', help='love in LastFM tracks of beets library')
+    import_beets_parser.add_argument('query', nargs='*', help='beets query, e.g. rating:5')
+    import_beets_parser.add_argument('--dry-run', action='store_true', help='only show found LastFM tracks')
+    import_lb_parser = subparsers.add_parser('import-listenbrainz', help='like in Spotify tracks loved in ListenBrainz')
+    import_lb_parser.add_argument('--dry-run', action='store_true', help='only show tracks which would be liked')
+    prune_parser = subparsers.add_parser('prune', help='forget state of tracks which are not liked in Spotify anymore')
+    prune_parser.add_argument('--dry-run', action='store_true', help='only show how many entries would be removed')
+    prune_parser.add_argument('--force', action='store_true',
+                              help='prune even if Spotify library shrank by more than MASS_REMOVAL_RATIO')
+    prune_parser.add_argument('--unlove', action='store_true',
+                              help='unlove the tracks in targets too, `restore-pruned` loves them again')
+    subparsers.add_parser('accept-library-size',
+                          help='accept current Spotify library sizes after sync warned about mass removal')
+    restore_pruned_parser = subparsers.add_parser('restore-pruned', help='love again tracks unloved by prune --unlove')
+    restore_pruned_parser.add_argument('--since', type=datetime.fromisoformat, metavar='DATE',
+                                       help='only tracks pruned since this date, e.g. 2024-05-01, all by default')
+    restore_pruned_parser.add_argument('--dry-run', action='store_true', help='only show tracks which would be loved')
+    unavailable_parser = subparsers.add_parser('unavailable', help='show liked tracks not playable in your market')
+    unavailable_parser.add_argument('--json', action='store_true', help='print JSON instead of table')
+    run_parser = subparsers.add_parser('run', help='run named pipelines from PIPELINES setting')
+    run_parser.add_argument('pipeline', nargs='?')
+    run_parser.add_argument('--all', action='store_true', help='run all pipelines')
+    serve_parser = subparsers.add_parser('serve', help='serve HTTP API to trigger sync and query its status')
+    serve_parser.add_argument('--listen', default='127.0.0.1:8080', help='host:port, default 127.0.0.1:8080')
+    serve_parser.add_argument('--schedule', help='cron expression for scheduled syncs, overrides SCHEDULE setting')
+    love_top_parser = subparsers.add_parser('love-top', help='love LastFM tracks you played many times')
+    love_top_parser.add_argument('--min-plays', type=int, help='overrides LOVE_TOP_MIN_PLAYS setting')
+    love_top_parser.add_argument('--like-spotify', action='store_true', help='like newly loved tracks in Spotify too')
+    snapshot_parser = subparsers.add_parser('snapshot', help='save Spotify liked tracks snapshot (default) or compare them')
+    snapshot_subparsers = snapshot_parser.add_subparsers(dest='snapshot_command')
+    snapshot_subparsers.add_parser('list', help='show saved snapshots')
+    snapshot_diff_parser = snapshot_subparsers.add_parser('diff', help='show tracks added/removed between snapshots')
+    snapshot_diff_parser.add_argument('old')
+    snapshot_diff_parser.add_argument('new')
+    bench_parser = subparsers.add_parser('bench', help='measure matching and cache performance on snapshot tracks')
+    bench_parser.add_argument('--snapshot', help='snapshot name, the latest one by default')
+    subparsers.add_parser('retry-unmatched', help='sync again tracks which were not found in targets')
+    unmatched_parser = subparsers.add_parser('unmatched', help='show, export or retry tracks not found in targets')
+    unmatched_parser.add_argument('--target', help='only tracks not found in this target')
+    unmatched_subparsers = unmatched_parser.add_subparsers(dest='unmatched_command')
+    unmatched_subparsers.add_parser('list', help='show tracks with what every matcher got (default)')
+    unmatched_export_parser = unmatched_subparsers.add_parser('export', help='write tracks to CSV file')
+    unmatched_export_parser.add_argument('file')
+    unmatched_subparsers.add_parser('retry', help='same as retry-unmatched')
+    subparsers.add_parser('retry-failed', help='sync again tracks which failed in the last sync run')
+    undo_parser = subparsers.add_parser('undo', help='unlove tracks loved by the sync run')
+    undo_parser.add_argument('run_id')
+    runs_parser = subparsers.add_parser('runs', help='show sync runs history')
+    runs_subparsers = runs_parser.add_subparsers(dest='runs_command')
+    runs_subparsers.add_parser('list', help='show all runs (default)')
+    runs_show_parser = runs_subparsers.add_parser('show', help='show run details')
+    runs_show_parser.add_argument('run_id')
+    audit_parser = subparsers.add_parser('audit',
+                                         help='show log of love, unlove, like, unlike and scrobble calls with results')
+    audit_parser.add_argument('--target', help='only calls to this target, e.g. lastfm or spotify')
+    audit_parser.add_argument('--action', choices=('love', 'unlove', 'like', 'unlike', 'scrobble'))
+    audit_parser.add_argument('--limit', type=int, default=50, help='show only the latest entries, default 50, 0 - all')
+    match_test_parser = subparsers.add_parser('match-test', help='show what every matcher finds in LastFM for the track')
+    match_test_parser.add_argument('artist')
+    match_test_parser.add_argument('name', help='song name')
+    query_parser = subparsers.add_parser('query', help='show how synced tracks were matched')
+    query_parser.add_argument('--confidence', help='condition like "<0.8" or ">=0.9"')
+    query_parser.add_argument('--method', help='exact, autocorrect, normalized, fuzzy, mbid or manual')
+    service_parser = subparsers.add_parser('install-service', help='generate systemd user service and timer')
+    service_parser.add_argument('--timer', default='daily', help='systemd OnCalendar value, default daily')
+    service_parser.add_argument('--env-file', help='file with syncer settings as environment variables')
+    service_parser.add_argument('--pipeline', help='run the pipeline instead of plain sync')
+    service_parser.add_argument('--install', action='store_true', help=f'write units to {USER_UNITS_DIR}')
+    cache_parser = subparsers.add_parser('cache', help='show or clear caches, sync state is kept')
+    cache_subparsers = cache_parser.add_subparsers(dest='cache_command', required=True)
+    cache_subparsers.add_parser('info', help='show cache files sizes, entries and modification times')
+    cache_clear_parser = cache_subparsers.add_parser('clear', help='remove cache files')
+    cache_clear_parser.add_argument('cache', choices=(
+        'spotify-token', 'search-cache', 'genres-cache', 'not-found', 'library-state', 'all',
+    ))
+    config_parser = subparsers.add_parser('config', help='show or change settings, see syncer/di_containers.py')
+    config_subparsers = config_parser.add_subparsers(dest='config_command', required=True)
+    config_get_parser = config_subparsers.add_parser('get', help='show effective setting value')
+    config_get_parser.add_argument('key', help='setting path, e.g. schedule or filters.min_popularity')
+    config_set_parser = config_subparsers.add_parser('set', help='validate and write setting into env file')
+    config_set_parser.add_argument('key', help='setting path, e.g. schedule or filters.min_popularity')
+    config_set_parser.add_argument('value', help='lists and dicts are JSON')
+    config_set_parser.add_argument('--env-file', default='.env', help='default .env')
+    args = parser.parse_args()
+
+    if args.command == 'install-service':
+        # units are generated without credentials, so settings and services are not needed
+        units = render_units(args.timer, env_file=args.env_file, pipeline=args.pipeline)
+        if args.install:
+            install_units(units)
+            print(f'installed to {USER_UNITS_DIR}, enable with: systemctl --user enable --now {UNIT_NAME}.timer')
+        else:
+            for name, content in units.items():
+                print(f'# {name}\n{content}')
+        sys.exit()
+
+    if args.command == 'config':
+        # settings are read directly, services are not created
+        try:
+            if args.config_command == 'get':
+                print(json.dumps(get_setting(args.key)))
+            else:
+                env_name = set_setting(args.key, args.value, env_file=args.env_file)
+                print(f'{env_name} is written to {args.env_file}')
+        except Exception as e:
+            config_parser.error(str(e))
+        sys.exit()
+
+    if args.command == 'unlove' and not args.from_file and not (args.artist and args.track):
+        unlove_parser.error('either --artist and --track or --from-file is required')
+    if args.command == 'run' and not args.all and not args.pipeline:
+        run_parser.error('either pipeline name or --all is required')
+    if args.command == 'review' and args.review_command in ('approve', 'reject') and not (args.track_id or args.all):
+        review_parser.error(f'{args.review_command} needs track ids or --all')
+    if args.command == 'sync' and args.report and not args.report.lower().endswith(REPORT_FORMATS):
+        sync_parser.error(f'--report file must be one of: {", ".join(REPORT_FORMATS)}')
+
+    container = init_container(read_only=args.read_only, log_file=args.log_file)
+    if args.command == 'serve' and not container.config.log_file():
+        # daemon output scrolls away in systemd or docker, so it is kept in a file too
+        container.config.log_file.override(SERVE_LOG_FILE)
+
+    try:
+        if args.command == 'status':
+            syncer = Syncer()
+            print_status(syncer.get_status(), get_next_runs(syncer, container.config.schedule()))
+        elif args.command == 'audit':
+            entries = Syncer().list_audit_log(target=args.target, action=args.action, limit=args.limit)
+            print_tracks_table([{**entry, 'run_id': entry['run_id'] or '-'} for entry in entries],
+                               ('at', 'target', 'action', 'track', 'result', 'run_id'))
+        elif args.command == 'whoami':
+            for name, user in Syncer().whoami().items():
+                print(f'{name}: {user}')
+        elif args.command == 'cache' and args.cache_command == 'info':
+            print_tracks_table(Syncer().get_caches_info(), ('cache', 'file', 'size', 'entries', 'modified'))
+        elif args.command == 'cache':
+            removed = Syncer().clear_caches(args.cache)
+            print(f'removed: {", ".join(removed) or "nothing"}')
+        elif args.command == 'diff':
+            print_diff(Syncer().diff_liked_tracks())
+        elif args.command == 'unlove':
+            tracks = read_tracks_file(args.from_file) if args.from_file else [(args.artist, args.track)]
+            Syncer().unlove_tracks(tracks, keep_skipped=args.keep_skipped)
+        elif args.command == 'tombstones' and args.tombstones_command == 'remove':
+            removed = Syncer().remove_tombstones(args.track_id)
+            print(f'removed: {len(removed)}')
+        elif args.command == 'tombstones':
+            print_tracks_table(Syncer().list_tombstones(), ('target', 'artist', 'name', 'track_id', 'created_at'))
+        elif args.command == 'review' and args.review_command in ('approve', 'reject'):
+            syncer = Syncer()
+            review = syncer.approve_reviewed if args.review_command == 'approve' else syncer.reject_reviewed
+            reviewed = review(track_ids=None if args.all else args.track_id)
+            print(f'{"loved" if args.review_command == "approve" else "rejected"}: {len(reviewed)}')
+        elif args.command == 'review':
+            print_tracks_table(
+                [{**match, 'confidence': f'{match["confidence"]:.2f}'}
+                 for match in Syncer().list_review_queue(target=args.target)],
+                ('target', 'artist', 'name', 'track_id', 'candidate', 'method', 'confidence'),
+            )
+        elif args.command == 'dedupe-spotify':
+            duplicates = Syncer().dedupe_spotify_liked_tracks(dry_run=args.dry_run)
+            print(f'duplicates: {len(duplicates)}')
+            for track in duplicates:
+                print(f'    {track["artist"]} - {track["name"]} ({track["id"]})')
+        elif args.command == 'love-album':
+            summary = Syncer().love_album(args.artist, args.album, target_names=args.target)
+            print(f'loved: {", ".join(f"{target} {count}" for target, count in summary["loved"].items())}')
+        elif args.command == 'love-now':
+            song = Syncer().love_now()
+            print(f'loved: {song["artist"]} - {song["name"]}' if song else 'nothing is playing')
+        elif args.command == 'sync-lastfm':
+            for target, tracks in Syncer().sync_lastfm_accounts(target_names=args.target, dry_run=args.dry_run).items():
+                print(f'{target}: {len(tracks)}')
+                for artist, name in tracks:
+                    print(f'    {artist} - {name}')
+        elif args.command == 'import-loved':
+            found = Syncer().import_loved_tracks(read_loved_export(args.file), dry_run=args.dry_run)
+            print_tracks_table(found, ('artist', 'name', 'spotify_id', 'liked'))
+        elif args.command == 'import-itunes':
+            tracks = read_itunes_library(args.library, args.min_rating)
+            print_tracks_table(Syncer().love_imported_tracks(tracks, dry_run=args.dry_run),
+                               ('artist', 'name', 'match', 'method'))
+        elif args.command == 'export-playlist':
+            if args.source == 'beets':
+                tracks = [
+                    {'artist': track['artist'], 'name': track['name'], 'location': track['path'], 'duration_secs': None}
+                    for track in read_beets_library(args.query)
+                ]
+            else:
+                tracks = Syncer().get_playlist_tracks(args.source)
+            output = args.output or f'{args.source}.{args.format}'
+            write_playlist(output, tracks, args.format)
+            print(f'{len(tracks)} tracks are written to {output}')
+        elif args.command == 'import-beets':
+            tracks = read_beets_library(args.query)
+            print_tracks_table(Syncer().love_imported_tracks(tracks, dry_run=args.dry_run),
+                               ('artist', 'name', 'match', 'method'))
+        elif args.command == 'import-listenbrainz':
+            imported = Syncer().import_listenbrainz_loves(dry_run=args.dry_run)
+            print_tracks_table(imported, ('artist', 'name', 'mbid', 'spotify_id', 'method'))
+        elif args.command == 'prune':
+            for name, count in Syncer().prune_state(dry_run=args.dry_run, force=args.force, unlove=args.unlove).items():
+                print(f'{name}: {count}')
+        elif args.command == 'accept-library-size':
+            for account, total in Syncer().accept_library_sizes().items():
+                print(f'{account}: {total} liked tracks')
+        elif args.command == 'restore-pruned':
+            restored = Syncer().restore_pruned(since=args.since, dry_run=args.dry_run)
+            print_tracks_table(restored, ('target', 'artist', 'name', 'track_id', 'pruned_at'))
+        elif args.command == 'unavailable':
+            tracks = Syncer().get_unavailable_liked_tracks()
+            if args.json:
+                print(json.dumps(tracks, indent=2))
+            else:
+                print_tracks_table(tracks, ('artist', 'name', 'id', 'restriction'))
+        elif args.command == 'run':
+            syncer = Syncer()
+            for name in (syncer.pipelines if args.all else [args.pipeline]):
+                syncer.run_pipeline(name)
+        elif args.command == 'love-top':
+            loved = Syncer().love_top_tracks(min_plays=args.min_plays, like_in_spotify=args.like_spotify)
+            print(f'loved: {len(loved)}')
+        elif args.command == 'snapshot':
+            syncer = Syncer()
+            if args.snapshot_command == 'list':
+                print('\n'.join(syncer.list_snapshots()))
+            elif args.snapshot_command == 'diff':
+                snapshots_diff = syncer.diff_snapshots(args.old, args.new)
+                for key in ('added', 'removed'):
+                    print(f'{key}: {len(snapshots_diff[key])}')
+                    for track in snapshots_diff[key]:
+                        print(f'    {track["artist"]} - {track["name"]} ({track["id"]})')
+            else:
+                print(syncer.create_snapshot())
+        elif args.command == 'bench':
+            syncer = Syncer()
+            if not args.snapshot and not syncer.list_snapshots():
+                bench_parser.error('there are no snapshots, run `snapshot` command first')
+            snapshot = args.snapshot or syncer.list_snapshots()[-1]
+            tracks = syncer.load_snapshot(snapshot)
+            print(f'snapshot: {snapshot}')
+            print(f'matching: {bench_matching(tracks, normalize_song_name)}')
+            print(f'cache: {bench_cache(tracks)}')
+        elif args.command == 'unmatched' and args.unmatched_command == 'retry':
+            Syncer().retry_unmatched()
+        elif args.command == 'unmatched' and args.unmatched_command == 'export':
+            write_unmatched_csv(args.file, Syncer().list_unmatched(target=args.target))
+        elif args.command == 'unmatched':
+            for track in Syncer().list_unmatched(target=args.target):
+                print(f'[{track["target"]}] {track["artist"]} - {track["name"]} ({track["track_id"]}), '
+                      f'retry after {track["retry_after"]}')
+                for attempt in track['attempts']:
+                    print(f'    {attempt["matcher"]}: {attempt["result"]}')
+        elif args.command == 'retry-unmatched':
+            Syncer().retry_unmatched()
+        elif args.command == 'retry-failed':
+            Syncer().retry_failed()
+        elif args.command == 'undo':
+            undone = Syncer().undo_run(args.run_id)
+            print(f'unloved: {len(undone)}')
+        elif args.command == 'runs':
+            if args.runs_command == 'show':
+                print_run(Syncer().get_run_details(args.run_id))
+            else:
+                print_runs(Syncer().list_runs())
+        elif args.command == 'match-test':
+            print_match_test(Syncer().match_test(args.artist, args.name))
+        elif args.command == 'query':
+            matches = Syncer().query_matches(confidence=args.confidence, method=args.method)
+            print_tracks_table(matches, ('target', 'artist', 'name', 'method', 'confidence', 'track_id'))
+        elif args.command == 'serve':
+            SyncerServer(Syncer(), args.listen, schedule=args.schedule or container.config.schedule()).serve_forever()
+        elif args.command == 'sync' and args.plan:
+            for target, tracks in Syncer().plan_sync().items():
+                print(f'{target}: {len(tracks)} tracks to sync')
+                for track in tracks:
+                    print(f'    {track["artist"]} - {track["name"]} ({track["id"]})')
+        elif args.command == 'sync':
+            track_ids = [parse_track_id(track) for track in args.track or []]
+            if args.from_file:
+                track_ids += read_track_ids_file(args.from_file)
+            syncer = Syncer()
+            summary = syncer.sync(interactive=args.interactive, force=args.force, resync=args.resync,
+                                  source=args.source, time_range=args.range, slow=args.slow,
+                                  track_ids=track_ids or None, artists=args.artist, order=args.order,
+                                  min_confidence=args.min_confidence, since_days=args.since_days)
+            if args.report:
+                write_report(args.report, syncer.get_run_details(summary['run_id']))
+                print(f'report is written to {args.report}')
+        else:
+            Syncer().sync()
+            # Syncer().sync_liked_tracks_from_lastfm_with_spotify()
+    except Exception as e:
+        error_kind = classify_error(e)
+        if error_kind == ErrorKind.UNKNOWN:
+            raise
+
+        print_redacted_exception(*sys.exc_info())
+        sys.exit(EXIT_CODES[error_kind])
+    finally:
+        container.shutdown_resources()
+
+
+if __name__ == "__main__":
+    main()
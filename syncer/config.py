@@ -0,0 +1,70 @@
+"""`config get` and `config set` commands.
+
+    Settings are environment variables or lines of .env file in working directory (see syncer/di_containers.py),
+    `config set` validates the value and writes it into .env or another env file, e.g. the one passed
+    to `install-service --env-file`.
+    Keys are setting paths like `schedule` or `filters.min_popularity`.
+"""
+
+import json
+import os
+from typing import Any
+
+from pydantic import BaseModel, ValidationError
+from pydantic.fields import SHAPE_SINGLETON, ModelField
+
+from syncer.di_containers import Settings
+
+
+def _find_field(key: str) -> ModelField:
+    model, field = Settings, None
+    for name in key.split('.'):
+        if field is not None:
+            model = field.type_
+        if not (isinstance(model, type) and issubclass(model, BaseModel)) or name not in model.__fields__:
+            raise Exception(f'unknown setting: {key}')
+        field = model.__fields__[name]
+
+    if field.shape == SHAPE_SINGLETON and isinstance(field.type_, type) and issubclass(field.type_, BaseModel):
+        raise Exception(f'{key} is a group of settings, use one of: {", ".join(field.type_.__fields__)}')
+    return field
+
+
+def get_setting(key: str) -> Any:
+    _find_field(key)
+    # JSON of settings has secrets masked
+    value = json.loads(Settings().json())
+    for name in key.split('.'):
+        value = value[name]
+    return value
+
+
+def set_setting(key: str, value: str, env_file: str = '.env') -> str:
+    """Validates the value and writes it into env file, returns name of environment variable."""
+    field = _find_field(key)
+    env_name = field.field_info.extra.get('env')
+    if not env_name:
+        raise Exception(f'{key} can not be set with environment variable')
+
+    try:
+        # lists and dicts are JSON, the same way pydantic reads them from environment
+        raw_value = json.loads(value) if field.is_complex() else value
+    except ValueError as e:
+        raise Exception(f'{key} must be JSON: {e}')
+
+    _, error = field.validate(raw_value, {}, loc=key)
+    if error:
+        raise Exception(str(ValidationError([error], Settings)))
+
+    lines = []
+    if os.path.exists(env_file):
+        with open(env_file) as f:
+            lines = [line for line in f.read().splitlines() if not line.startswith(f'{env_name}=')]
+    # JSON values have quotes and spaces, single quotes keep them for shell and systemd EnvironmentFile
+    lines.append(f"{env_name}='{value}'" if field.is_complex() or ' ' in value else f'{env_name}={value}')
+
+    # env file has secrets, so it is owner-only
+    with os.fdopen(os.open(env_file, os.O_WRONLY | os.O_CREAT | os.O_TRUNC, 0o600), 'w') as f:
+        os.fchmod(f.fileno(), 0o600)
+        f.write('\n'.join(lines) + '\n')
+    return env_name
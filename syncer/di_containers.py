@@ -1,43 +1,291 @@
 """Containers module."""
 
 import logging
-from pydantic import BaseSettings, Field
+import logging.handlers
+import os
+from typing import Any, Dict, List, Literal, Optional
+
+from pydantic import BaseModel, BaseSettings, Field, SecretStr, validator
 
 from dependency_injector import containers, providers
 
 
+from services.bandcamp import BandcampService
+from services.funkwhale import FunkwhaleService
 from services.last_fm import LastFmService
+from services.libre_fm import LibreFmService
+from services.listenbrainz import ListenBrainzService
+from services.mpd import MpdService
+from services.qobuz import QobuzService
+from services.soundcloud import SoundCloudService
 from services.spotify import SpotifyService
+from syncer.logs import RedactingFilter, get_log_handler
+
+
+class EnvFileSettings(BaseSettings):
+    class Config:
+        # `config set` writes settings there, environment variables take precedence
+        env_file = '.env'
+
 
+# secrets are SecretStr, so they are masked in reprs, logs and `config get` output,
+# services unwrap them only when handing them to API clients
+class LastFMAccount(BaseModel):
+    user: str
+    password: SecretStr
 
-class LastFMConfig(BaseSettings):
+
+class LastFMConfig(EnvFileSettings):
     __prefix = 'LASTFM'
 
     user: str = Field(env=f'{__prefix}_USER')
-    password: str = Field(env=f'{__prefix}_PASSWORD')
-    api_key: str = Field(env=f'{__prefix}_API_KEY')
-    api_secret: str = Field(env=f'{__prefix}_API_SECRET')
+    password: SecretStr = Field(env=f'{__prefix}_PASSWORD')
+    api_key: SecretStr = Field(env=f'{__prefix}_API_KEY')
+    api_secret: SecretStr = Field(env=f'{__prefix}_API_SECRET')
+    # JSON object of extra accounts, e.g. '{"partner": {"user": "...", "password": "..."}}',
+    # they are `lastfm-<name>` targets and get loves of the main account with `sync-lastfm` command
+    extra_accounts: Dict[str, LastFMAccount] = Field({}, env=f'{__prefix}_EXTRA_ACCOUNTS')
+
+
+class LibreFMConfig(EnvFileSettings):
+    __prefix = 'LIBREFM'
+
+    user: Optional[str] = Field(None, env=f'{__prefix}_USER')
+    password: Optional[SecretStr] = Field(None, env=f'{__prefix}_PASSWORD')
 
-class SpotifyConfig(BaseSettings):
+
+class ListenBrainzConfig(EnvFileSettings):
+    __prefix = 'LISTENBRAINZ'
+
+    token: Optional[SecretStr] = Field(None, env=f'{__prefix}_TOKEN')
+
+
+class SpotifyConfig(EnvFileSettings):
     __prefix = 'SPOTIFY'
 
     client_id: str = Field(env=f'{__prefix}_CLIENT_ID')
-    client_secret: str = Field(env=f'{__prefix}_SECRET')
+    # without secret Spotify is authorized with PKCE flow (and catalog lookups need authorized user)
+    client_secret: Optional[SecretStr] = Field(None, env=f'{__prefix}_SECRET')
+    market: Optional[str] = Field(None, env=f'{__prefix}_MARKET')
+    # JSON list of extra account names, e.g. '["partner", "kids"]', liked tracks of all accounts are synced
+    accounts: List[str] = Field([], env=f'{__prefix}_ACCOUNTS')
+
+
+class FunkwhaleConfig(EnvFileSettings):
+    """Funkwhale instance, favorite tracks of the token user are synced as `funkwhale` source."""
+    __prefix = 'FUNKWHALE'
+
+    url: Optional[str] = Field(None, env=f'{__prefix}_URL')
+    token: Optional[SecretStr] = Field(None, env=f'{__prefix}_TOKEN')
+
+
+class SoundCloudConfig(EnvFileSettings):
+    """SoundCloud OAuth access token, liked tracks of its user are synced as `soundcloud` source."""
+    __prefix = 'SOUNDCLOUD'
+
+    token: Optional[SecretStr] = Field(None, env=f'{__prefix}_TOKEN')
+
+
+class BandcampConfig(EnvFileSettings):
+    """Bandcamp fan page user name, collection tracks are synced as `bandcamp` source."""
+    __prefix = 'BANDCAMP'
+
+    username: Optional[str] = Field(None, env=f'{__prefix}_USERNAME')
+
+
+class QobuzConfig(EnvFileSettings):
+    """Qobuz app id and user auth token, favorite tracks are synced as `qobuz` source."""
+    __prefix = 'QOBUZ'
+
+    app_id: Optional[str] = Field(None, env=f'{__prefix}_APP_ID')
+    user_auth_token: Optional[SecretStr] = Field(None, env=f'{__prefix}_USER_AUTH_TOKEN')
+
+
+class MpdConfig(EnvFileSettings):
+    """MPD or Mopidy server for `love-now` command."""
+    __prefix = 'MPD'
+
+    host: str = Field('localhost', env=f'{__prefix}_HOST')
+    port: int = Field(6600, env=f'{__prefix}_PORT')
+    password: Optional[SecretStr] = Field(None, env=f'{__prefix}_PASSWORD')
+
+
+class HooksConfig(EnvFileSettings):
+    """Shell commands run around `sync`, they get JSON with sync summary (or error) on stdin."""
+    __prefix = 'HOOKS'
 
+    pre_sync: Optional[str] = Field(None, env=f'{__prefix}_PRE_SYNC')
+    post_sync: Optional[str] = Field(None, env=f'{__prefix}_POST_SYNC')
+    on_failure: Optional[str] = Field(None, env=f'{__prefix}_ON_FAILURE')
 
-class Settings(BaseSettings):
-    lastfm: LastFMConfig = LastFMConfig()
-    spotify: SpotifyConfig = SpotifyConfig()
+
+class FiltersConfig(EnvFileSettings):
+    """Liked tracks skipped by sync, genres are JSON lists matched as substrings of Spotify artist genres."""
+    __prefix = 'FILTERS'
+
+    genres_include: List[str] = Field([], env=f'{__prefix}_GENRES_INCLUDE')
+    genres_exclude: List[str] = Field([], env=f'{__prefix}_GENRES_EXCLUDE')
+    # skips interludes, sound effects and so on
+    min_duration_secs: Optional[int] = Field(None, env=f'{__prefix}_MIN_DURATION_SECS')
+    # Spotify popularity is 0-100
+    min_popularity: Optional[int] = Field(None, env=f'{__prefix}_MIN_POPULARITY')
+    # `skip` explicit tracks or sync `only` explicit tracks
+    explicit: Optional[Literal['skip', 'only']] = Field(None, env=f'{__prefix}_EXPLICIT')
+
+
+class RewriteRule(BaseModel):
+    # `artist`, `name` (song name) or `both`
+    field: Literal['artist', 'name', 'both'] = 'name'
+    # python regex, replaced with `replace` (which can refer groups like \1)
+    pattern: str
+    replace: str = ''
+
+
+class PipelineConfig(BaseModel):
+    # Spotify account names, `default` is the one from SPOTIFY_* settings, or other sources, e.g. `funkwhale`, `soundcloud`, `bandcamp`, `qobuz`
+    sources: List[str] = ['default']
+    targets: List[str] = ['lastfm']
+    # overrides of FILTERS_* settings for this pipeline, keys are lower case names without prefix,
+    # e.g. {"genres_include": ["jazz"], "min_popularity": 20}
+    filters: Dict[str, Any] = {}
+    # cron expression, `serve` runs the pipeline at scheduled times besides SCHEDULE syncs
+    schedule: Optional[str] = None
+
+    @validator('filters')
+    def check_filters(cls, filters: Dict[str, Any]) -> Dict[str, Any]:
+        unknown = set(filters) - set(FiltersConfig.__fields__)
+        if unknown:
+            raise ValueError(f'unknown filters: {sorted(unknown)}, use: {", ".join(FiltersConfig.__fields__)}')
+        # values are validated like FILTERS_* settings
+        validated = FiltersConfig(**filters)
+        return {name: getattr(validated, name) for name in filters}
+
+
+class Settings(EnvFileSettings):
+    lastfm: LastFMConfig = Field(default_factory=LastFMConfig)
+    spotify: SpotifyConfig = Field(default_factory=SpotifyConfig)
+    # optional targets, tracks are loved there too when credentials are set
+    librefm: LibreFMConfig = Field(default_factory=LibreFMConfig)
+    listenbrainz: ListenBrainzConfig = Field(default_factory=ListenBrainzConfig)
+    # optional sources, their liked tracks are synced together with Spotify ones
+    funkwhale: FunkwhaleConfig = Field(default_factory=FunkwhaleConfig)
+    soundcloud: SoundCloudConfig = Field(default_factory=SoundCloudConfig)
+    bandcamp: BandcampConfig = Field(default_factory=BandcampConfig)
+    qobuz: QobuzConfig = Field(default_factory=QobuzConfig)
+    mpd: MpdConfig = Field(default_factory=MpdConfig)
+    # JSON object of named pipelines, e.g. '{"family": {"sources": ["default", "partner"], "targets": ["lastfm"],
+    # "filters": {"min_popularity": 20}, "schedule": "0 4 * * *"}}'
+    pipelines: Dict[str, PipelineConfig] = Field({}, env='PIPELINES')
+    # python file with `on_track(track)` function, see Syncer._load_track_hook
+    track_hook: Optional[str] = Field(None, env='TRACK_HOOK')
+    hooks: HooksConfig = Field(default_factory=HooksConfig)
+    filters: FiltersConfig = Field(default_factory=FiltersConfig)
+    # ntfy topic url, e.g. https://ntfy.sh/my-syncer, gets a message when sync finishes or fails
+    ntfy_url: Optional[str] = Field(None, env='NTFY_URL')
+    # healthchecks.io ping url, pinged with /start, /fail or without suffix on success
+    heartbeat_url: Optional[str] = Field(None, env='HEARTBEAT_URL')
+    # Sentry DSN, fatal sync errors and per-sync failure stats are reported there (needs sentry-sdk installed)
+    sentry_dsn: Optional[SecretStr] = Field(None, env='SENTRY_DSN')
+    # cron expression for scheduled syncs in `serve` mode, e.g. '0 3 * * *'
+    schedule: Optional[str] = Field(None, env='SCHEDULE')
+    # `love-top` command loves tracks played more times than this
+    love_top_min_plays: int = Field(50, env='LOVE_TOP_MIN_PLAYS')
+    # JSON list, order of strategies for finding Spotify tracks in targets, see syncer/matching.py,
+    # e.g. append "transliterated" for non-Latin artists
+    matchers: List[str] = Field(['exact', 'autocorrect', 'normalized', 'featuring', 'fuzzy', 'mbid'], env='MATCHERS')
+    # JSON list of regex substitutions applied in order before matching, check them with `match-test` command, e.g.
+    # '[{"field": "name", "pattern": " - From \\"[^\\"]+\\" Soundtrack$"}]'
+    rewrite_rules: List[RewriteRule] = Field([], env='REWRITE_RULES')
+    # `sync --interactive` asks user to confirm matches with lower confidence
+    confirm_confidence: float = Field(0.9, env='CONFIRM_CONFIDENCE')
+    # matches with lower confidence (e.g. fuzzy ones) are not loved by sync, they wait for `review approve`,
+    # all matches are loved when it's not set, `sync --min-confidence` overrides it
+    min_confidence: Optional[float] = Field(None, env='MIN_CONFIDENCE')
+    # tracks not found in targets are not searched again for this number of days
+    not_found_retry_days: int = Field(7, env='NOT_FOUND_RETRY_DAYS')
+    # found target tracks are not searched again for this number of days
+    search_cache_days: int = Field(30, env='SEARCH_CACHE_DAYS')
+    # `sync --slow` pause between love calls
+    slow_love_interval_secs: float = Field(1.0, env='SLOW_LOVE_INTERVAL_SECS')
+    # sync scrobbles track at Spotify like time before loving it, so LastFM library shows when track was discovered,
+    # only likes of the last 14 days are scrobbled (LastFM ignores older scrobbles)
+    backdate_scrobbles: bool = Field(False, env='BACKDATE_SCROBBLES')
+    # sync fetches only Spotify likes of this number of last days instead of the whole library, e.g. 7 for scheduled
+    # syncs of `serve`, older likes are expected to be synced already, `sync --since-days` overrides it
+    liked_since_days: Optional[int] = Field(None, env='LIKED_SINCE_DAYS')
+    # sync warns (and notifies) when Spotify library shrinks by more than this part since the last accepted size,
+    # e.g. when Spotify API returns partial library, `prune` refuses to forget state then unless `--force` is given,
+    # `accept-library-size` command accepts the new size
+    mass_removal_ratio: float = Field(0.5, env='MASS_REMOVAL_RATIO')
+    # sync fails instead of warning about Spotify library shrinking by more than MASS_REMOVAL_RATIO
+    abort_on_mass_removal: bool = Field(False, env='ABORT_ON_MASS_REMOVAL')
+    # JSON object of API calls limits of one sync by service (lastfm, spotify, librefm, listenbrainz, musicbrainz),
+    # e.g. '{"lastfm": 5000}', tracks left after the limit are synced next time, usage is shown by `status` command
+    max_calls_per_run: Dict[str, int] = Field({}, env='MAX_CALLS_PER_RUN')
+    # User-Agent of all API calls, e.g. 'my-syncer/1.0 (me@example.com)', host name is appended unless disabled
+    user_agent: Optional[str] = Field(None, env='USER_AGENT')
+    user_agent_append_host: bool = Field(True, env='USER_AGENT_APPEND_HOST')
+    # forbids changing state files and liked tracks, same as `--read-only` flag
+    read_only: bool = Field(False, env='READ_ONLY')
+    # logs are written to this file too (rotated at 10MB, 5 old files are kept), `serve` uses syncer.log by default
+    log_file: Optional[str] = Field(None, env='LOG_FILE')
+    # logs are sent to syslog or journald (needs systemd-python) too, with run_id and track_id fields
+    log_backend: Optional[Literal['syslog', 'journald']] = Field(None, env='LOG_BACKEND')
     logger_name: str = 'app_syncer'
 
 
-def get_logger(logger_name):
+def get_logger(logger_name, log_file=None, log_backend=None):
     logging.basicConfig(level=logging.INFO)
     logger = logging.getLogger(logger_name)
     logger.setLevel(logging.INFO)
+    # logger is a factory, so the file handler is added only once per file
+    if log_file and not any(getattr(handler, 'baseFilename', None) == os.path.abspath(log_file)
+                            for handler in logger.handlers):
+        handler = logging.handlers.RotatingFileHandler(log_file, maxBytes=10 * 1024 * 1024, backupCount=5)
+        handler.setFormatter(logging.Formatter('%(asctime)s %(levelname)s %(message)s'))
+        logger.addHandler(handler)
+    if log_backend and not any(getattr(handler, 'log_backend', None) == log_backend for handler in logger.handlers):
+        logger.addHandler(get_log_handler(log_backend))
+    for handler in logging.getLogger().handlers + logger.handlers:
+        if not any(isinstance(log_filter, RedactingFilter) for log_filter in handler.filters):
+            handler.addFilter(RedactingFilter())
     return logger
 
 
+def get_extra_spotify_services(accounts, client_id, client_secret, logger, market) -> List[SpotifyService]:
+    return [
+        SpotifyService(client_id, client_secret, logger, market=market, account=account)
+        for account in accounts
+    ]
+
+
+def get_extra_sources(funkwhale: Dict, soundcloud: Dict, bandcamp: Dict, qobuz: Dict, logger) -> List[Any]:
+    sources = []
+    if funkwhale['url'] and funkwhale['token']:
+        sources.append(FunkwhaleService(funkwhale['url'], funkwhale['token'], logger))
+    if soundcloud['token']:
+        sources.append(SoundCloudService(soundcloud['token'], logger))
+    if bandcamp['username']:
+        sources.append(BandcampService(bandcamp['username'], logger))
+    if qobuz['app_id'] and qobuz['user_auth_token']:
+        sources.append(QobuzService(qobuz['app_id'], qobuz['user_auth_token'], logger))
+    return sources
+
+
+def get_extra_targets(lastfm: Dict, librefm: Dict, listenbrainz: Dict, logger) -> Dict[str, Any]:
+    targets = {}
+    for name, account in lastfm['extra_accounts'].items():
+        # every account has own session, API key is shared
+        targets[f'lastfm-{name}'] = LastFmService(
+            account['user'], account['password'], lastfm['api_key'], lastfm['api_secret'], logger,
+        )
+    if librefm['user'] and librefm['password']:
+        targets['librefm'] = LibreFmService(librefm['user'], librefm['password'], logger)
+    if listenbrainz['token']:
+        targets['listenbrainz'] = ListenBrainzService(listenbrainz['token'], logger)
+    return targets
+
+
 class DIContainer(containers.DeclarativeContainer):
 
     config = providers.Configuration()
@@ -46,6 +294,8 @@ class DIContainer(containers.DeclarativeContainer):
     logger = providers.Factory(
         get_logger,
         logger_name=config.logger_name,
+        log_file=config.log_file,
+        log_backend=config.log_backend,
     )
     lastfm_service = providers.Singleton(
         LastFmService,
@@ -61,4 +311,39 @@ class DIContainer(containers.DeclarativeContainer):
         client_id=config.spotify.client_id,
         client_secret=config.spotify.client_secret,
         logger=logger,
+        market=config.spotify.market,
+    )
+
+    extra_spotify_services = providers.Singleton(
+        get_extra_spotify_services,
+        accounts=config.spotify.accounts,
+        client_id=config.spotify.client_id,
+        client_secret=config.spotify.client_secret,
+        logger=logger,
+        market=config.spotify.market,
+    )
+
+    mpd_service = providers.Singleton(
+        MpdService,
+        host=config.mpd.host,
+        port=config.mpd.port,
+        password=config.mpd.password,
+        logger=logger,
+    )
+
+    extra_sources = providers.Singleton(
+        get_extra_sources,
+        funkwhale=config.funkwhale,
+        soundcloud=config.soundcloud,
+        bandcamp=config.bandcamp,
+        qobuz=config.qobuz,
+        logger=logger,
+    )
+
+    extra_targets = providers.Singleton(
+        get_extra_targets,
+        lastfm=config.lastfm,
+        librefm=config.librefm,
+        listenbrainz=config.listenbrainz,
+        logger=logger,
     )
\ No newline at end of file
@@ -0,0 +1,53 @@
+"""User-Agent of all API calls, some self-hosted targets (GNU FM, Maloja) require a meaningful one
+    and it tells API providers which client made the calls, see USER_AGENT setting.
+"""
+
+import http.client
+import socket
+from typing import Optional
+from urllib.parse import urlsplit
+
+import requests
+
+from syncer import __version__
+from syncer.usage import count_call
+
+# https://musicbrainz.org/doc/MusicBrainz_API/Rate_Limiting#Provide_meaningful_User-Agent_strings
+DEFAULT_USER_AGENT = f'lastfm-spotify-syncer/{__version__} ( https://github.com/nickmetal/lastfm-spotify-syncer )'
+
+_user_agent = DEFAULT_USER_AGENT
+_original_putheader = http.client.HTTPConnection.putheader
+
+
+def set_user_agent(user_agent: Optional[str] = None, append_host: bool = True):
+    """Sets User-Agent of sessions created after this call, host name is appended to tell installations apart."""
+    global _user_agent
+    _user_agent = user_agent or DEFAULT_USER_AGENT
+    if append_host:
+        _user_agent += f' (host {socket.gethostname()})'
+    # pylast has no option for User-Agent, its `pylast/<version>` header is replaced on http.client level
+    http.client.HTTPConnection.putheader = _putheader
+
+
+def get_user_agent() -> str:
+    return _user_agent
+
+
+def _putheader(connection, header, *values):
+    if header == 'User-Agent' and values and str(values[0]).startswith('pylast/'):
+        values = (_user_agent,)
+    return _original_putheader(connection, header, *values)
+
+
+class ApiSession(requests.Session):
+    """Session of service clients, its calls are counted in API usage (see syncer/usage.py)."""
+
+    def request(self, method, url, *args, **kwargs):
+        count_call(urlsplit(url).hostname)
+        return super().request(method, url, *args, **kwargs)
+
+
+def new_session() -> requests.Session:
+    session = ApiSession()
+    session.headers['User-Agent'] = _user_agent
+    return session
@@ -0,0 +1,116 @@
+"""Log handlers and filters: syslog and journald ones with run and track ids as separate fields
+    (e.g. `journalctl RUN_ID=<id>`), redaction of secrets from log records and error messages.
+"""
+
+import contextvars
+import logging
+import logging.handlers
+import os
+import re
+from typing import Any, Iterator, Optional, Set
+
+from pydantic import SecretStr
+
+SYSLOG_IDENTIFIER = 'lastfm-spotify-syncer'
+
+# Spotify track which is synced now, set by sync loop
+current_track_id = contextvars.ContextVar('current_track_id', default=None)
+# sync run in progress, e.g. for audit log entries
+current_run_id = contextvars.ContextVar('current_run_id', default=None)
+
+REDACTED = '***'
+# settings with these words in names are secrets, e.g. `api_secret` or `user_auth_token`
+SECRET_SETTING_WORDS = ('password', 'secret', 'token', 'api_key', 'dsn')
+# third-party errors embed request urls and bodies with keys, e.g. LastFM `api_sig` and `sk` (session key)
+SECRET_PARAMS_RE = re.compile(
+    r'\b((?:api_key|api_sig|sk|token|access_token|refresh_token|client_secret|password|authToken)'
+    r'(?:=[\'"]?|[\'"]?:\s*[\'"]?))[^&\s\'",}]+'
+)
+AUTH_HEADER_RE = re.compile(r'\b(Bearer|Basic|Token) [^\s\'",}]+')
+
+_secrets: Set[str] = set()
+
+
+def add_secrets(*values: Optional[str]):
+    """Registers secret values, they are replaced in logs and error messages wherever they appear."""
+    # too short values would redact random words
+    _secrets.update(value for value in values if value and len(value) >= 6)
+
+
+def find_secrets(settings: Any) -> Iterator[str]:
+    """Yields values of secret settings from Settings.dict(), nested groups and accounts included."""
+    if isinstance(settings, dict):
+        for key, value in settings.items():
+            if isinstance(value, SecretStr):
+                yield value.get_secret_value()
+            elif isinstance(value, str) and any(word in key for word in SECRET_SETTING_WORDS):
+                yield value
+            else:
+                yield from find_secrets(value)
+    elif isinstance(settings, list):
+        for value in settings:
+            yield from find_secrets(value)
+
+
+def redact(text: str) -> str:
+    # longer secrets first, so a secret containing another one is not left half redacted
+    for secret in sorted(_secrets, key=len, reverse=True):
+        text = text.replace(secret, REDACTED)
+    text = SECRET_PARAMS_RE.sub(rf'\1{REDACTED}', text)
+    return AUTH_HEADER_RE.sub(rf'\1 {REDACTED}', text)
+
+
+def redact_data(data: Any) -> Any:
+    """Returns copy of JSON-like data (e.g. Sentry event) with secrets redacted from all strings."""
+    if isinstance(data, str):
+        return redact(data)
+    if isinstance(data, dict):
+        return {key: redact_data(value) for key, value in data.items()}
+    if isinstance(data, (list, tuple)):
+        return [redact_data(value) for value in data]
+    return data
+
+
+class RedactingFilter(logging.Filter):
+    """Redacts secrets from messages and tracebacks, it is added to handlers, so records of third-party loggers
+        (spotipy, urllib3 and so on) are redacted too.
+    """
+
+    def filter(self, record: logging.LogRecord) -> bool:
+        message = record.getMessage()
+        redacted = redact(message)
+        if redacted != message:
+            record.msg, record.args = redacted, None
+        if record.exc_info and not record.exc_text:
+            # formatters use cached exc_text instead of formatting exc_info again
+            record.exc_text = redact(logging.Formatter().formatException(record.exc_info))
+        return True
+
+
+class LogFieldsFilter(logging.Filter):
+    """Adds run id (set by RunIdFilter during sync) and track id to records, upper case ones are journald fields."""
+
+    def filter(self, record: logging.LogRecord) -> bool:
+        record.run_id = getattr(record, 'run_id', None)
+        record.track_id = current_track_id.get()
+        record.RUN_ID = record.run_id or ''
+        record.TRACK_ID = record.track_id or ''
+        return True
+
+
+def get_log_handler(backend: str) -> logging.Handler:
+    if backend == 'journald':
+        # needs systemd-python, it sends upper case record attributes as journal fields
+        from systemd.journal import JournalHandler
+
+        handler = JournalHandler(SYSLOG_IDENTIFIER=SYSLOG_IDENTIFIER)
+    else:
+        address = '/dev/log' if os.path.exists('/dev/log') else ('localhost', 514)
+        handler = logging.handlers.SysLogHandler(address=address)
+        # syslog has no custom fields, so they are key=value pairs in the message
+        handler.setFormatter(logging.Formatter(
+            f'{SYSLOG_IDENTIFIER}[%(process)d]: run_id=%(run_id)s track_id=%(track_id)s %(message)s'
+        ))
+    handler.log_backend = backend
+    handler.addFilter(LogFieldsFilter())
+    return handler
@@ -0,0 +1,364 @@
+"""Strategies for finding Spotify tracks in target services.
+
+    Strategies are tried in configured order (MATCHERS setting), the first found track wins:
+
+    exact        target track with the same artist and song name
+    autocorrect  artist and song names corrected by LastFM
+    normalized   song name without remaster/version suffixes
+    featuring    featured artists moved between song and artist names, e.g. `Song (feat. X)` -> `Artist feat. X - Song`
+    fuzzy        the most similar LastFM search result
+    mbid         LastFM track by MusicBrainz recording id of the track (or of the Spotify track ISRC)
+    transliterated  romanized (e.g. Cyrillic, Japanese, Korean) artist and song name, not enabled by default
+
+    Before matching artist and song names are rewritten with REWRITE_RULES regex substitutions.
+    Names are compared after unicode normalization (fullwidth forms, smart quotes) and case folding.
+    Found tracks are cached by artist and song name without re-release suffixes (see `duplicate_name_key`),
+    so they are not searched again until cache expires. Other recordings (e.g. `Song - Live Version`) are cached apart.
+"""
+
+import re
+import unicodedata
+from datetime import datetime, timedelta
+from typing import Any, Dict, List, NamedTuple, Optional, Tuple
+
+from fuzzywuzzy import fuzz
+from unidecode import unidecode
+
+from syncer.logs import redact
+
+from services.errors import ErrorKind, classify_error
+from services.last_fm import LastFmService
+from services.musicbrainz import MusicBrainzService
+
+VERSION_SUFFIX = re.compile(
+    r'(\s+-\s+|\s*\(|\s*\[)[^-()\[\]]*\b(remaster(ed)?|version|mono|stereo|edit)\b[^-()\[\]]*[)\]]?\s*$',
+    re.IGNORECASE,
+)
+
+# suffixes of re-released same recording, unlike `Live Version`, `Acoustic Version` or `Extended Edit`
+# which are other recordings, e.g. `Song - 2011 Remaster` or `Song (Single Version)`
+RERELEASE_SUFFIX = re.compile(
+    r'(\s+-\s+|\s*\(|\s*\[)\s*('
+    r'(\d{4}\s+)?(digital(ly)?\s+)?remaster(ed)?(\s+\d{4})?(\s+version)?'
+    r'|(single|album|lp)\s+version|(mono|stereo)(\s+version)?'
+    r')\s*[)\]]?\s*$',
+    re.IGNORECASE,
+)
+# services raise errors of these kinds for not found tracks (invalid service data included), the rest
+# (rate limits, network, auth and unknown ones like bugs in matchers) fail the track and aren't cached as not found
+NOT_FOUND_KINDS = {ErrorKind.NOT_FOUND, ErrorKind.DATA}
+# re-releases of the same recording differ in length by a few seconds at most
+DUPLICATE_MAX_DURATION_DIFF_MS = 5000
+
+
+PLAIN_PUNCTUATION = str.maketrans({
+    '\u2018': "'", '\u2019': "'", '\u201c': '"', '\u201d': '"', '\u2010': '-', '\u2013': '-', '\u2014': '-',
+})
+
+# `Song (feat. X)`, `Song [ft. X]`, `Song - featuring X`
+FEATURING_IN_SONG = re.compile(r'\s*(?:-\s*|\(|\[)?\s*\b(?:feat\.?|ft\.|featuring)\s+([^()\[\]]+?)\s*[)\]]?\s*$', re.IGNORECASE)
+# `Artist feat. X`
+FEATURING_IN_ARTIST = re.compile(r'^(.+?)\s+\b(?:feat\.?|ft\.|featuring)\s+(.+)$', re.IGNORECASE)
+
+
+def normalize_unicode(text: str) -> str:
+    """Composes characters and replaces fullwidth forms and smart quotes/dashes with plain ones."""
+    return unicodedata.normalize('NFKC', text).translate(PLAIN_PUNCTUATION)
+
+
+def fold_name(text: str) -> str:
+    """Returns artist or song name for case insensitive comparison, e.g. `Straße` and `STRASSE` are equal."""
+    return normalize_unicode(text).casefold().strip()
+
+
+def normalize_song_name(name: str) -> str:
+    """Strips remaster/version suffixes, e.g. `Song - Remastered 2011` or `Song (Single Version)`."""
+    return fold_name(VERSION_SUFFIX.sub('', normalize_unicode(name)))
+
+
+def is_not_found_error(error: Exception) -> bool:
+    return classify_error(error) in NOT_FOUND_KINDS
+
+
+def duplicate_name_key(artist: str, name: str) -> Tuple[str, str]:
+    """Returns artist and song name without re-release suffixes, tracks with the same key may be duplicates."""
+    return fold_name(artist), fold_name(RERELEASE_SUFFIX.sub('', normalize_unicode(name)))
+
+
+def is_same_recording(track: Dict, other: Dict) -> bool:
+    """Returns whether Spotify tracks are the same recording: the same ISRC or the same artist and song name
+        without re-release suffixes and close durations (unknown durations are not compared).
+    """
+    if track.get('isrc') and track.get('isrc') == other.get('isrc'):
+        return True
+    if duplicate_name_key(track['artist'], track['name']) != duplicate_name_key(other['artist'], other['name']):
+        return False
+
+    durations = track.get('duration_ms') or 0, other.get('duration_ms') or 0
+    return not all(durations) or abs(durations[0] - durations[1]) <= DUPLICATE_MAX_DURATION_DIFF_MS
+
+
+def find_duplicates(tracks: List[Dict], synced: List[Dict]) -> Dict[str, Dict]:
+    """Returns tracks which are other releases of the same recording by track id, with the track which is kept
+        instead of them: an already synced one (from `synced`), otherwise the earliest liked one.
+        Order of `tracks` doesn't matter, tracks with unknown like time count as the earliest ones.
+    """
+    kept_by_name: Dict[Tuple[str, str], List[Dict]] = {}
+    kept_by_isrc: Dict[str, Dict] = {}
+    duplicates = {}
+    synced_ids = {track['id'] for track in synced}
+    earliest_first = sorted(tracks, key=lambda track: track.get('added_at') or '')
+
+    for track in [*synced, *(track for track in earliest_first if track['id'] not in synced_ids)]:
+        name_key = duplicate_name_key(track['artist'], track['name'])
+        same_recordings = [kept_by_isrc[track['isrc']]] if track.get('isrc') in kept_by_isrc else []
+        same_recordings += [kept for kept in kept_by_name.get(name_key, []) if is_same_recording(kept, track)]
+        original = next((kept for kept in same_recordings if kept['id'] != track['id']), None)
+        if original:
+            duplicates[track['id']] = original
+            continue
+
+        kept_by_name.setdefault(name_key, []).append(track)
+        if track.get('isrc'):
+            kept_by_isrc.setdefault(track['isrc'], track)
+    return duplicates
+
+
+class Match(NamedTuple):
+    # whatever target service `like_track` accepts
+    target_track: Any
+    method: str
+    confidence: float
+
+
+class Matcher:
+    name = ''
+
+    def __init__(self, logger) -> None:
+        self.logger = logger
+
+    def match(self, service, track: Dict) -> Optional[Match]:
+        raise NotImplementedError
+
+
+class ExactMatcher(Matcher):
+    name = 'exact'
+
+    def match(self, service, track: Dict) -> Optional[Match]:
+        return Match(service.get_track(track['artist'], track['name']), self.name, 1.0)
+
+
+class AutocorrectMatcher(Matcher):
+    name = 'autocorrect'
+
+    def match(self, service, track: Dict) -> Optional[Match]:
+        if not isinstance(service, LastFmService):
+            return None
+        return Match(service.get_corrected_track(track['artist'], track['name']), self.name, 0.9)
+
+
+class NormalizedMatcher(Matcher):
+    name = 'normalized'
+
+    def match(self, service, track: Dict) -> Optional[Match]:
+        artist = normalize_unicode(track['artist']).strip()
+        song_name = VERSION_SUFFIX.sub('', normalize_unicode(track['name'])).strip()
+        if (artist, song_name) == (track['artist'], track['name']):
+            return None
+        return Match(service.get_track(artist, song_name), self.name, 0.95)
+
+
+class FeaturingMatcher(Matcher):
+    """LastFM and Spotify disagree where featured artists live, tries song and artist names moved around."""
+    name = 'featuring'
+
+    @staticmethod
+    def get_candidates(artist: str, song_name: str) -> List[Tuple[str, str]]:
+        candidates = []
+        in_song = FEATURING_IN_SONG.search(song_name)
+        if in_song:
+            plain_song_name = song_name[:in_song.start()].strip()
+            candidates += [(artist, plain_song_name), (f'{artist} feat. {in_song.group(1)}', plain_song_name)]
+
+        in_artist = FEATURING_IN_ARTIST.match(artist)
+        if in_artist:
+            main_artist, featured = in_artist.groups()
+            candidates += [(main_artist, song_name), (main_artist, f'{song_name} (feat. {featured})')]
+        return candidates
+
+    def match(self, service, track: Dict) -> Optional[Match]:
+        for artist, song_name in self.get_candidates(track['artist'], track['name']):
+            try:
+                return Match(service.get_track(artist, song_name), self.name, 0.9)
+            except Exception as e:
+                if not is_not_found_error(e):
+                    raise
+                self.logger.debug(f'no track {artist} - {song_name}: {e}')
+
+
+class FuzzyMatcher(Matcher):
+    name = 'fuzzy'
+    match_ratio = 85
+
+    def get_candidates(self, service, track: Dict) -> List[Tuple[Any, int, int]]:
+        """Returns LastFM search results with artist and song name similarity ratios (0-100)."""
+        if not isinstance(service, LastFmService):
+            return []
+
+        return [
+            (
+                found_track,
+                fuzz.ratio(fold_name(track['artist']), fold_name(found_track.artist.name)),
+                fuzz.ratio(normalize_song_name(track['name']), normalize_song_name(found_track.get_name())),
+            )
+            for found_track in service.search_tracks(track['artist'], track['name'])
+        ]
+
+    def match(self, service, track: Dict) -> Optional[Match]:
+        best_match = None
+
+        for found_track, artist_ratio, song_ratio in self.get_candidates(service, track):
+            confidence = min(artist_ratio, song_ratio) / 100
+
+            if artist_ratio >= self.match_ratio and song_ratio >= self.match_ratio:
+                if not best_match or confidence > best_match.confidence:
+                    best_match = Match(found_track, self.name, confidence)
+
+        return best_match
+
+
+class MbidMatcher(Matcher):
+    name = 'mbid'
+
+    def __init__(self, logger) -> None:
+        super().__init__(logger)
+        self.musicbrainz = MusicBrainzService(logger)
+
+    def match(self, service, track: Dict) -> Optional[Match]:
+        if not isinstance(service, LastFmService) or not (track.get('mbid') or track.get('isrc')):
+            return None
+
+        mbids = [track['mbid']] if track.get('mbid') else self.musicbrainz.get_recording_ids(track['isrc'])
+        for mbid in mbids:
+            try:
+                return Match(service.get_track_by_mbid(mbid), self.name, 1.0)
+            except Exception as e:
+                if not is_not_found_error(e):
+                    raise
+                self.logger.debug(f'no LastFM track with mbid {mbid}: {e}')
+
+
+class TransliteratedMatcher(Matcher):
+    """LastFM often has non-Latin artists under romanized names. Romanized names can't be converted back,
+        so Latin Spotify names of artists known to LastFM in native script are left to autocorrect and mbid.
+    """
+    name = 'transliterated'
+
+    def match(self, service, track: Dict) -> Optional[Match]:
+        artist, song_name = unidecode(track['artist']).strip(), unidecode(track['name']).strip()
+        if (artist, song_name) == (track['artist'], track['name']):
+            return None
+        return Match(service.get_track(artist, song_name), self.name, 0.8)
+
+
+MATCHERS = {matcher.name: matcher for matcher in (
+    ExactMatcher, AutocorrectMatcher, NormalizedMatcher, FeaturingMatcher, FuzzyMatcher, MbidMatcher,
+    TransliteratedMatcher,
+)}
+
+
+class MatcherChain:
+    def __init__(self, names: List[str], logger, cache_ttl_days: int = 30,
+                 rewrite_rules: Optional[List[Dict]] = None) -> None:
+        unknown = set(names) - set(MATCHERS)
+        if unknown:
+            raise Exception(f'unknown matchers: {sorted(unknown)}, available: {list(MATCHERS)}')
+
+        self.logger = logger
+        self.matchers = [MATCHERS[name](logger) for name in names]
+        # ordered (fields, compiled pattern, replacement) substitutions
+        self.rewrite_rules = [
+            (['artist', 'name'] if rule['field'] == 'both' else [rule['field']], re.compile(rule['pattern']), rule['replace'])
+            for rule in rewrite_rules or []
+        ]
+        self.cache_ttl_days = cache_ttl_days
+        # `<service class>:<artist>|<song name>` -> found track, loaded and stored by syncer
+        self.cache: Dict[str, Dict] = {}
+
+    @staticmethod
+    def get_cache_key(service, track: Dict) -> str:
+        """Returns cache key of rewritten track, re-releases of the same recording share it."""
+        artist, song_name = duplicate_name_key(track['artist'], track['name'])
+        return f'{type(service).__name__}:{artist}|{song_name}'
+
+    def rewrite(self, track: Dict) -> Dict:
+        """Returns track with artist and song names changed by rewrite rules."""
+        track = dict(track)
+        for fields, pattern, replace in self.rewrite_rules:
+            for field in fields:
+                track[field] = pattern.sub(replace, track[field]).strip()
+        return track
+
+    def match(self, service, track: Dict, attempts: Optional[List[Dict]] = None) -> Optional[Match]:
+        """Returns the first match of matchers, `attempts` list (when given) gets what every tried matcher got."""
+        track = self.rewrite(track)
+        cache_key = self.get_cache_key(service, track)
+        cached = self.cache.get(cache_key)
+        if cached and datetime.fromisoformat(cached['expires_at']) > datetime.now():
+            self.logger.debug(f'found cached match for {track}')
+            return Match(service.load_track(cached['track']), cached['method'], cached['confidence'])
+
+        found = self._match(service, track, attempts)
+        if found:
+            self.cache[cache_key] = {
+                'track': service.dump_track(found.target_track),
+                'method': found.method,
+                'confidence': found.confidence,
+                'expires_at': (datetime.now() + timedelta(days=self.cache_ttl_days)).isoformat(),
+            }
+        return found
+
+    def forget(self, service, track: Dict) -> bool:
+        """Drops cached match of the track (e.g. rejected by user), returns whether it was cached."""
+        return self.cache.pop(self.get_cache_key(service, self.rewrite(track)), None) is not None
+
+    def explain(self, service, track: Dict) -> List[Dict]:
+        """Runs every matcher (not only until the first match) and returns what each of them found,
+            fuzzy matcher reports all search candidates with similarity ratios.
+        """
+        results = []
+        for matcher in self.matchers:
+            result = {'matcher': matcher.name, 'found': None, 'confidence': None, 'error': None, 'candidates': []}
+            try:
+                found = matcher.match(service, track)
+                if found:
+                    result.update(found=str(found.target_track), confidence=found.confidence)
+                if isinstance(matcher, FuzzyMatcher):
+                    result['candidates'] = [
+                        {'track': str(candidate), 'artist_ratio': artist_ratio, 'song_ratio': song_ratio}
+                        for candidate, artist_ratio, song_ratio in matcher.get_candidates(service, track)
+                    ]
+            except Exception as e:
+                result['error'] = str(e)
+            results.append(result)
+        return results
+
+    def _match(self, service, track: Dict, attempts: Optional[List[Dict]] = None) -> Optional[Match]:
+        attempts = attempts if attempts is not None else []
+        for matcher in self.matchers:
+            attempt = {'matcher': matcher.name, 'result': 'not found'}
+            attempts.append(attempt)
+            try:
+                found = matcher.match(service, track)
+            except Exception as e:
+                # services raise errors for not found tracks, other errors fail the track, so it's not cached
+                # as not found and sync backs off or re-authenticates
+                if not is_not_found_error(e):
+                    raise
+                self.logger.debug(f'{matcher.name} matcher failed for {track}: {e}')
+                attempt['result'] = f'error: {redact(str(e))}'
+                continue
+
+            if found:
+                attempt['result'] = f'found with confidence {found.confidence}'
+                return found
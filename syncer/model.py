@@ -1,4 +1,51 @@
-from pydantic import BaseModel
+import unicodedata
+from typing import Dict, Optional
+
+from pydantic import BaseModel, validator
+
+
+class Track(BaseModel):
+    """Spotify track as it is passed around as dict: to filters, track hook, snapshots, HTTP API and exports.
+
+        Fields are only added to this JSON shape, never renamed or removed, so user scripts keep working.
+    """
+    artist: str
+    artist_id: Optional[str] = None
+    name: str
+    id: str
+    isrc: Optional[str] = None
+    # open.spotify.com link
+    url: Optional[str] = None
+    # all ids Spotify knows, e.g. isrc, ean, upc
+    external_ids: Dict[str, str] = {}
+    # duration, popularity and explicit flag are None when the source doesn't know them (e.g. Bandcamp)
+    duration_ms: Optional[int] = None
+    # Spotify popularity is 0-100
+    popularity: Optional[int] = None
+    explicit: Optional[bool] = None
+    # is_playable, restriction and linked_from are set only when market is given
+    is_playable: bool = True
+    restriction: Optional[str] = None
+    # MusicBrainz recording id, known for tracks from non-Spotify sources
+    mbid: Optional[str] = None
+    # original id of relinked track, Spotify returns playable in the market track instead of it
+    linked_from: Optional[str] = None
+    # ISO 8601 time when track was liked, known for Spotify liked tracks
+    added_at: Optional[str] = None
+    # `track`, `episode` (podcast), `chapter` (audiobook) or `local` (local file), only tracks are synced
+    item_type: str = 'track'
+
+    class Config:
+        # fields are not changed after validation, tracks are not hashable though because of `external_ids` dict
+        frozen = True
+
+    @validator('artist', 'name')
+    def clean_name(cls, value: str) -> str:
+        """Trims whitespace and composes unicode characters, so garbage names never reach matchers."""
+        value = unicodedata.normalize('NFC', value).strip()
+        if not value:
+            raise ValueError('must not be empty')
+        return value
 
 
 class SyncTrack(BaseModel):
@@ -9,3 +56,7 @@ class SyncTrack(BaseModel):
     # spotify_song: str
 
     spotify_track_uri: str
+
+    # exact, fuzzy or manual (confirmed by user), see syncer/matching.py for other sync methods
+    match_method: str = 'exact'
+    match_confidence: float = 1.0
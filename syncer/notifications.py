@@ -0,0 +1,94 @@
+"""Push notifications and heartbeat pings about syncs, failures to notify never fail the sync itself."""
+
+from typing import Dict
+
+import requests
+from pydantic import SecretStr
+
+from syncer import __version__
+from syncer.identity import get_user_agent
+from syncer.logs import redact_data
+
+
+def format_summary(summary: Dict) -> str:
+    message = (
+        f'loved {sum(summary["loved"].values())}, not found {sum(summary["not_found"].values())}, '
+        f'failed {sum(summary["failed"].values())} of {summary["liked"]} liked tracks'
+    )
+    for mass_removal in summary.get('mass_removals', []):
+        message += f'. WARNING: {mass_removal}'
+    return message
+
+
+class NtfyNotifier:
+    """Publishes messages to ntfy.sh (or self-hosted ntfy) topic url, e.g. https://ntfy.sh/my-syncer."""
+
+    def __init__(self, url: str, logger) -> None:
+        self.url = url
+        self.logger = logger
+
+    def notify(self, title: str, message: str, failed: bool = False):
+        # https://docs.ntfy.sh/publish/
+        headers = {'Title': title, 'Tags': 'warning' if failed else 'musical_note', 'User-Agent': get_user_agent()}
+        if failed:
+            headers['Priority'] = 'high'
+
+        try:
+            requests.post(self.url, data=message.encode(), headers=headers, timeout=10).raise_for_status()
+        except Exception as e:
+            self.logger.warning(f'ntfy notification is not sent: {e}')
+
+
+class HeartbeatPinger:
+    """Pings healthchecks.io style url when sync starts, succeeds and fails, so missed or failed runs are noticed."""
+
+    def __init__(self, url: str, logger) -> None:
+        self.url = url.rstrip('/')
+        self.logger = logger
+
+    def ping(self, event: str = '', message: str = ''):
+        # https://healthchecks.io/docs/http_api/, empty event is success
+        url = f'{self.url}/{event}' if event else self.url
+        try:
+            headers = {'User-Agent': get_user_agent()}
+            requests.post(url, data=message.encode(), headers=headers, timeout=10).raise_for_status()
+        except Exception as e:
+            self.logger.warning(f'heartbeat ping {event or "success"} is not sent: {e}')
+
+
+class SentryReporter:
+    """Reports fatal sync errors and per-track failure stats to Sentry, sentry-sdk is imported only when enabled."""
+
+    def __init__(self, dsn: SecretStr, logger) -> None:
+        import sentry_sdk
+
+        self.sentry_sdk = sentry_sdk
+        self.logger = logger
+        # uncaught exceptions of any command are reported by default integrations too
+        sentry_sdk.init(
+            dsn=dsn.get_secret_value(),
+            release=f'lastfm-spotify-syncer@{__version__}',
+            # frame locals hold tokens and API secrets
+            with_locals=False,
+            send_default_pii=False,
+            # events and breadcrumbs of logging integration don't pass logging filters, so they are redacted here
+            before_send=lambda event, hint: redact_data(event),
+            before_breadcrumb=lambda crumb, hint: redact_data(crumb),
+        )
+
+    def capture_error(self, error: Exception, run_id: str):
+        with self.sentry_sdk.push_scope() as scope:
+            scope.set_tag('run_id', run_id)
+            self.sentry_sdk.capture_exception(error)
+
+    def report_failures(self, summary: Dict):
+        """Sends one event per sync with failed tracks counted by error kind, not an event per track."""
+        if not summary['failures_by_kind']:
+            return
+
+        with self.sentry_sdk.push_scope() as scope:
+            scope.set_tag('run_id', summary['run_id'])
+            scope.set_context('failures_by_kind', summary['failures_by_kind'])
+            for kind in summary['failures_by_kind']:
+                scope.set_tag(f'failed.{kind}', True)
+            self.sentry_sdk.capture_message(f'sync failed for {sum(summary["failed"].values())} tracks', level='warning')
@@ -0,0 +1,31 @@
+import time
+
+
+class AdaptiveDelay:
+    """Pause between API calls which grows on rate limit/server errors and shrinks back while calls succeed.
+
+        Sync calls every target (match and love) one track at a time, so the pause is what limits their request
+        rate. It's not thread-safe and isn't used by concurrent calls, e.g. Spotify liked tracks pages fetched
+        in parallel rely on spotipy retries of rate limited calls instead.
+    """
+
+    def __init__(self, logger, max_delay_secs: float = 60.0, min_delay_secs: float = 0.5) -> None:
+        self.logger = logger
+        self.max_delay_secs = max_delay_secs
+        self.min_delay_secs = min_delay_secs
+        self.delay_secs = 0.0
+
+    def wait(self):
+        if self.delay_secs:
+            time.sleep(self.delay_secs)
+
+    def success(self):
+        if not self.delay_secs:
+            return
+
+        self.delay_secs = self.delay_secs / 2 if self.delay_secs / 2 >= self.min_delay_secs else 0.0
+        self.logger.info(f'API is healthy, pause between calls: {self.delay_secs:.1f}s')
+
+    def failure(self):
+        self.delay_secs = min(self.max_delay_secs, max(self.min_delay_secs, self.delay_secs * 2))
+        self.logger.warning(f'API is rate limited or failing, pause between calls: {self.delay_secs:.1f}s')
@@ -0,0 +1,54 @@
+"""M3U and XSPF playlists for `export-playlist` command.
+
+    Tracks are {'artist': ..., 'name': ..., 'location': ..., 'duration_secs': ...}, location is Spotify or LastFM
+    url for streaming sources and file path for local ones (beets), duration may be unknown (None).
+"""
+
+import os
+from typing import Dict, List
+from urllib.parse import quote
+from xml.sax.saxutils import escape
+
+PLAYLIST_FORMATS = ('m3u', 'xspf')
+
+
+def render_m3u(tracks: List[Dict]) -> str:
+    lines = ['#EXTM3U']
+    for track in tracks:
+        duration = track['duration_secs'] if track.get('duration_secs') is not None else -1
+        lines.append(f'#EXTINF:{duration},{track["artist"]} - {track["name"]}')
+        lines.append(track['location'])
+    return '\n'.join(lines) + '\n'
+
+
+def to_uri(location: str) -> str:
+    if location.startswith(('http://', 'https://')):
+        return location
+    return f'file://{quote(os.path.abspath(location))}'
+
+
+def render_xspf(tracks: List[Dict]) -> str:
+    # https://xspf.org/spec, locations are URIs, so file paths become file:// ones
+    lines = [
+        '<?xml version="1.0" encoding="UTF-8"?>',
+        '<playlist version="1" xmlns="http://xspf.org/ns/0/">',
+        '  <trackList>',
+    ]
+    for track in tracks:
+        lines += [
+            '    <track>',
+            f'      <location>{escape(to_uri(track["location"]))}</location>',
+            f'      <creator>{escape(track["artist"])}</creator>',
+            f'      <title>{escape(track["name"])}</title>',
+        ]
+        if track.get('duration_secs') is not None:
+            lines.append(f'      <duration>{track["duration_secs"] * 1000}</duration>')
+        lines.append('    </track>')
+    lines += ['  </trackList>', '</playlist>']
+    return '\n'.join(lines) + '\n'
+
+
+def write_playlist(path: str, tracks: List[Dict], playlist_format: str):
+    renderers = {'m3u': render_m3u, 'xspf': render_xspf}
+    with open(path, 'w') as f:
+        f.write(renderers[playlist_format](tracks))
@@ -0,0 +1,88 @@
+"""Sync run reports for `sync --report`, format is chosen by file extension: .md, .json or .html.
+
+    Reports list loved, failed and not found tracks, not found ones have LastFM search links for fixing mismatches.
+"""
+
+import html
+import json
+import os
+from typing import Dict, List, Tuple
+from urllib.parse import quote_plus
+
+REPORT_FORMATS = ('.md', '.json', '.html')
+
+
+def lastfm_search_url(artist: str, name: str) -> str:
+    return f'https://www.last.fm/search/tracks?q={quote_plus(f"{artist} {name}")}'
+
+
+def _report_sections(run: Dict) -> List[Tuple[str, List[Dict]]]:
+    not_found = [{**track, 'search_url': lastfm_search_url(track['artist'], track['name'])} for track in run['not_found']]
+    return [
+        ('Not found', not_found),
+        ('Failed', run.get('summary', {}).get('failures', [])),
+        ('Loved', run['loved']),
+    ]
+
+
+def _summary_lines(run: Dict) -> List[str]:
+    summary = run.get('summary', {})
+    lines = [f'started {run["started_at"]}, duration {run.get("duration_secs", 0):.1f}s']
+    if run.get('error'):
+        lines.append(f'error: {run["error"]}')
+    if summary:
+        lines.append(f'liked tracks: {summary["liked"]}')
+        for key in ('loved', 'not_found', 'failed'):
+            lines.append(f'{key.replace("_", " ")}: ' + ', '.join(f'{t} {count}' for t, count in summary[key].items()))
+    return lines
+
+
+def render_markdown(run: Dict) -> str:
+    lines = [f'# Sync {run["run_id"]}', ''] + [f'- {line}' for line in _summary_lines(run)]
+    for title, tracks in _report_sections(run):
+        lines += ['', f'## {title} ({len(tracks)})', '']
+        for track in tracks:
+            line = f'- [{track["target"]}] {track["artist"]} - {track["name"]}'
+            if track.get('search_url'):
+                line += f' ([search in LastFM]({track["search_url"]}))'
+            if track.get('error'):
+                line += f': {track["phase"]} failed: {track["error"]}'
+            lines.append(line)
+    return '\n'.join(lines) + '\n'
+
+
+def render_html(run: Dict) -> str:
+    parts = [
+        f'<!doctype html>\n<html>\n<head><meta charset="utf-8"><title>Sync {html.escape(run["run_id"])}</title></head>',
+        f'<body>\n<h1>Sync {html.escape(run["run_id"])}</h1>\n<ul>',
+    ]
+    parts += [f'<li>{html.escape(line)}</li>' for line in _summary_lines(run)]
+    parts.append('</ul>')
+    for title, tracks in _report_sections(run):
+        parts.append(f'<h2>{title} ({len(tracks)})</h2>\n<ul>')
+        for track in tracks:
+            item = html.escape(f'[{track["target"]}] {track["artist"]} - {track["name"]}')
+            if track.get('search_url'):
+                item += f' (<a href="{html.escape(track["search_url"])}">search in LastFM</a>)'
+            if track.get('error'):
+                item += html.escape(f': {track["phase"]} failed: {track["error"]}')
+            parts.append(f'<li>{item}</li>')
+        parts.append('</ul>')
+    parts.append('</body>\n</html>')
+    return '\n'.join(parts) + '\n'
+
+
+def render_json(run: Dict) -> str:
+    sections = {title.lower().replace(' ', '_'): tracks for title, tracks in _report_sections(run)}
+    return json.dumps({**run, **sections}, indent=2) + '\n'
+
+
+def write_report(path: str, run: Dict):
+    """Writes report of run from Syncer.get_run_details."""
+    extension = os.path.splitext(path)[1].lower()
+    renderers = {'.md': render_markdown, '.json': render_json, '.html': render_html}
+    if extension not in renderers:
+        raise Exception(f'unknown report format: {path}, use one of: {", ".join(REPORT_FORMATS)}')
+
+    with open(path, 'w') as f:
+        f.write(renderers[extension](run))
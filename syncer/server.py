@@ -0,0 +1,331 @@
+"""HTTP API for driving the syncer from other services.
+
+    POST /sync     starts sync in background, 409 if sync is already running
+    GET  /status   current state and last sync summary
+    GET  /history  summaries of syncs run by this server
+    GET  /pending  low confidence matches waiting for `review`
+    GET  /unmatched tracks not found in targets
+    GET  /events   server-sent events stream with syncer log messages
+    GET  /feed.atom Atom feed of recently loved tracks
+    GET  /         dashboard page built on top of the endpoints above
+
+    With cron schedule (e.g. `0 3 * * *`) server also runs syncs at scheduled times,
+    pipelines with own `schedule` (see PIPELINES setting) are run at their times too.
+    Runs missed while the server was down are caught up with one sync on start.
+"""
+
+import json
+import logging
+import queue
+import re
+import threading
+import time
+from datetime import datetime
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from typing import Dict, List, Optional
+from urllib.parse import quote
+from xml.sax.saxutils import escape, quoteattr
+
+from croniter import croniter
+
+
+DASHBOARD_HTML = """<!doctype html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>Likes syncer</title>
+  <style>
+    body { font-family: sans-serif; max-width: 60em; margin: 2em auto; }
+    pre { background: #f4f4f4; padding: 1em; max-height: 20em; overflow: auto; }
+    table { border-collapse: collapse; }
+    td, th { text-align: left; padding: 0.2em 0.5em; border-bottom: 1px solid #ddd; }
+  </style>
+</head>
+<body>
+  <h1>Likes syncer</h1>
+  <p>Status: <b id="state">loading</b> <button id="sync">Sync now</button></p>
+  <h2>Last run</h2>
+  <pre id="last-run"></pre>
+  <h2>History</h2>
+  <pre id="history"></pre>
+  <h2>Pending review (<span id="pending-count">0</span>)</h2>
+  <table id="pending"></table>
+  <h2>Unmatched (<span id="unmatched-count">0</span>)</h2>
+  <table id="unmatched"></table>
+  <h2>Log</h2>
+  <pre id="log"></pre>
+  <script>
+    function fillTable(id, rows, columns) {
+      const table = document.getElementById(id);
+      table.replaceChildren();
+      document.getElementById(id + '-count').textContent = rows.length;
+      [columns, ...rows.map(row => columns.map(column => row[column]))].forEach((values, i) => {
+        const tr = table.insertRow();
+        values.forEach(value => {
+          const cell = document.createElement(i ? 'td' : 'th');
+          cell.textContent = value;
+          tr.appendChild(cell);
+        });
+      });
+    }
+    async function refresh() {
+      const status = await (await fetch('/status')).json();
+      const history = await (await fetch('/history')).json();
+      document.getElementById('state').textContent =
+        status.running ? 'running' : (status.last_error ? 'failed: ' + status.last_error : 'idle');
+      document.getElementById('sync').disabled = status.running;
+      document.getElementById('last-run').textContent = JSON.stringify(status.last_run, null, 2);
+      document.getElementById('history').textContent =
+        history.slice().reverse().map(run => JSON.stringify(run)).join('\\n');
+      const pending = await (await fetch('/pending')).json();
+      fillTable('pending', pending.map(match => ({...match, confidence: match.confidence.toFixed(2)})),
+                ['target', 'artist', 'name', 'candidate', 'method', 'confidence']);
+      const unmatched = await (await fetch('/unmatched')).json();
+      fillTable('unmatched', unmatched, ['target', 'artist', 'name', 'retry_after']);
+    }
+    document.getElementById('sync').onclick = async () => {
+      await fetch('/sync', {method: 'POST'});
+      refresh();
+    };
+    const log = document.getElementById('log');
+    new EventSource('/events').onmessage = event => {
+      log.textContent += event.data + '\\n';
+      log.scrollTop = log.scrollHeight;
+    };
+    refresh();
+    setInterval(refresh, 5000);
+  </script>
+</body>
+</html>
+"""
+
+
+def render_atom_feed(tracks: List[Dict]) -> str:
+    """Returns Atom feed of loved tracks from Syncer.get_recently_loved, entries link Spotify and LastFM tracks."""
+    # feed and entry times must have time zone, stored times are local
+    def atom_time(value: str) -> str:
+        return datetime.fromisoformat(value).astimezone().isoformat()
+
+    updated = atom_time(tracks[0]['loved_at']) if tracks else datetime.now().astimezone().isoformat()
+    lines = [
+        '<?xml version="1.0" encoding="utf-8"?>',
+        '<feed xmlns="http://www.w3.org/2005/Atom">',
+        '  <title>Loved tracks</title>',
+        '  <id>urn:lastfm-spotify-syncer:loved</id>',
+        f'  <updated>{updated}</updated>',
+    ]
+    for track in tracks:
+        lastfm_url = f'https://www.last.fm/music/{quote(track["artist"], safe="")}/_/{quote(track["name"], safe="")}'
+        entry_id = f'urn:lastfm-spotify-syncer:{track["target"]}:{track["track_id"]}'
+        lines += [
+            '  <entry>',
+            f'    <title>{escape(track["artist"])} - {escape(track["name"])}</title>',
+            f'    <id>{escape(entry_id)}</id>',
+            f'    <updated>{atom_time(track["loved_at"])}</updated>',
+            f'    <link rel="related" href={quoteattr(lastfm_url)}/>',
+        ]
+        # tracks of other sources (e.g. `mpd:...`) have no Spotify page
+        if re.fullmatch(r'[0-9A-Za-z]{22}', track['track_id']):
+            lines.append(f'    <link href="https://open.spotify.com/track/{track["track_id"]}"/>')
+        lines += [
+            f'    <summary>loved in {escape(track["target"])} ({escape(track["method"])} match)</summary>',
+            '  </entry>',
+        ]
+    lines.append('</feed>')
+    return '\n'.join(lines) + '\n'
+
+
+def next_scheduled_run(syncer, schedule: str, pipeline: Optional[str] = None) -> datetime:
+    """Returns time of the next scheduled sync (or pipeline run) of `serve`, past time means missed runs
+        which are caught up right away.
+    """
+    return croniter(schedule, syncer.load_last_scheduled_run(pipeline) or datetime.now()).get_next(datetime)
+
+
+class EventsHandler(logging.Handler):
+    """Broadcasts log messages to all SSE subscribers."""
+
+    def __init__(self) -> None:
+        super().__init__()
+        self._subscribers: List[queue.Queue] = []
+        self._lock = threading.Lock()
+
+    def subscribe(self) -> queue.Queue:
+        subscriber = queue.Queue()
+        with self._lock:
+            self._subscribers.append(subscriber)
+        return subscriber
+
+    def unsubscribe(self, subscriber: queue.Queue):
+        with self._lock:
+            self._subscribers.remove(subscriber)
+
+    def emit(self, record: logging.LogRecord):
+        message = self.format(record)
+        with self._lock:
+            for subscriber in self._subscribers:
+                subscriber.put(message)
+
+
+class SyncerServer:
+    def __init__(self, syncer, listen: str, schedule: Optional[str] = None) -> None:
+        host, port = listen.rsplit(':', 1)
+        self.syncer = syncer
+        self.address = (host, int(port))
+        self.schedule = schedule
+        self.next_run: Optional[datetime] = None
+        self.pipeline_schedules: Dict[str, str] = {
+            name: pipeline['schedule'] for name, pipeline in syncer.pipelines.items() if pipeline['schedule']
+        }
+        self.pipeline_next_runs: Dict[str, datetime] = {}
+        self.running = False
+        self.last_error: Optional[str] = None
+        self.history: List[Dict] = []
+        self._lock = threading.Lock()
+        self.events = EventsHandler()
+        self.syncer.logger.addHandler(self.events)
+
+    def status(self) -> Dict:
+        return {
+            'running': self.running,
+            'last_error': self.last_error,
+            'last_run': self.history[-1] if self.history else None,
+            'schedule': self.schedule,
+            'next_run': self.next_run.isoformat() if self.next_run else None,
+            'pipelines': {
+                name: {
+                    'schedule': schedule,
+                    'next_run': self.pipeline_next_runs[name].isoformat() if name in self.pipeline_next_runs else None,
+                }
+                for name, schedule in self.pipeline_schedules.items()
+            },
+        }
+
+    def trigger_sync(self, pipeline: Optional[str] = None) -> bool:
+        with self._lock:
+            if self.running:
+                return False
+            self.running = True
+
+        threading.Thread(target=self._sync, args=(pipeline,), daemon=True).start()
+        return True
+
+    def _sync(self, pipeline: Optional[str] = None):
+        started_at = datetime.now().isoformat()
+        run = {'started_at': started_at, 'pipeline': pipeline} if pipeline else {'started_at': started_at}
+        try:
+            summary = self.syncer.run_pipeline(pipeline) if pipeline else self.syncer.sync()
+            self.last_error = None
+            self.history.append({**run, 'summary': summary})
+        except Exception as e:
+            self.syncer.logger.exception('sync failed')
+            self.last_error = str(e)
+            self.history.append({**run, 'error': str(e)})
+        finally:
+            self.running = False
+
+    def _schedule_loop(self, schedule: str, pipeline: Optional[str] = None):
+        last_run = self.syncer.load_last_scheduled_run(pipeline) or datetime.now()
+        what = f'pipeline {pipeline}' if pipeline else 'sync'
+
+        while True:
+            # when next run is already in the past (missed runs) sync starts right away, only once
+            next_run = croniter(schedule, last_run).get_next(datetime)
+            if pipeline:
+                self.pipeline_next_runs[pipeline] = next_run
+            else:
+                self.next_run = next_run
+            self.syncer.logger.info(f'next scheduled {what}: {next_run}')
+
+            delay = (next_run - datetime.now()).total_seconds()
+            if delay > 0:
+                time.sleep(delay)
+
+            if not self.trigger_sync(pipeline):
+                self.syncer.logger.info(f'skip scheduled {what}, sync is already running')
+
+            last_run = datetime.now()
+            # read-only syncer keeps state untouched, scheduled syncs fail then and the loop goes on
+            if not self.syncer.read_only:
+                self.syncer.dump_last_scheduled_run(last_run, pipeline)
+
+    def serve_forever(self):
+        server = self
+
+        class Handler(BaseHTTPRequestHandler):
+            def do_GET(self):
+                if self.path == '/':
+                    self._send_html(DASHBOARD_HTML)
+                elif self.path == '/status':
+                    self._send_json(200, server.status())
+                elif self.path == '/history':
+                    self._send_json(200, server.history)
+                elif self.path == '/pending':
+                    self._send_json(200, server.syncer.list_review_queue())
+                elif self.path == '/unmatched':
+                    self._send_json(200, server.syncer.list_unmatched())
+                elif self.path == '/events':
+                    self._stream_events()
+                elif self.path == '/feed.atom':
+                    self._send_body(render_atom_feed(server.syncer.get_recently_loved()), 'application/atom+xml')
+                else:
+                    self._send_json(404, {'error': 'not found'})
+
+            def do_POST(self):
+                if self.path != '/sync':
+                    self._send_json(404, {'error': 'not found'})
+                elif server.trigger_sync():
+                    self._send_json(202, {'started': True})
+                else:
+                    self._send_json(409, {'error': 'sync is already running'})
+
+            def _send_json(self, code: int, data):
+                body = json.dumps(data).encode()
+                self.send_response(code)
+                self.send_header('Content-Type', 'application/json')
+                self.send_header('Content-Length', str(len(body)))
+                self.end_headers()
+                self.wfile.write(body)
+
+            def _send_html(self, html: str):
+                self._send_body(html, 'text/html')
+
+            def _send_body(self, text: str, content_type: str):
+                body = text.encode()
+                self.send_response(200)
+                self.send_header('Content-Type', f'{content_type}; charset=utf-8')
+                self.send_header('Content-Length', str(len(body)))
+                self.end_headers()
+                self.wfile.write(body)
+
+            def _stream_events(self):
+                self.send_response(200)
+                self.send_header('Content-Type', 'text/event-stream')
+                self.send_header('Cache-Control', 'no-cache')
+                self.end_headers()
+
+                subscriber = server.events.subscribe()
+                try:
+                    while True:
+                        try:
+                            message = subscriber.get(timeout=15)
+                            self.wfile.write(f'data: {message}\n\n'.encode())
+                        except queue.Empty:
+                            # keep-alive comment, also detects closed connections
+                            self.wfile.write(b': ping\n\n')
+                        self.wfile.flush()
+                except (BrokenPipeError, ConnectionResetError):
+                    pass
+                finally:
+                    server.events.unsubscribe(subscriber)
+
+            def log_message(self, format, *args):
+                server.syncer.logger.debug(f'{self.address_string()} {format % args}')
+
+        if self.schedule:
+            threading.Thread(target=self._schedule_loop, args=(self.schedule,), daemon=True).start()
+        for name, schedule in self.pipeline_schedules.items():
+            threading.Thread(target=self._schedule_loop, args=(schedule, name), daemon=True).start()
+
+        self.syncer.logger.info(f'serve syncer API on {self.address[0]}:{self.address[1]}')
+        ThreadingHTTPServer(self.address, Handler).serve_forever()
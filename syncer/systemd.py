@@ -0,0 +1,56 @@
+"""Systemd user service and timer units for periodic syncs."""
+
+import os
+import sys
+from typing import Dict, Optional
+
+UNIT_NAME = 'lastfm-spotify-syncer'
+USER_UNITS_DIR = os.path.expanduser('~/.config/systemd/user')
+
+SERVICE_TEMPLATE = """[Unit]
+Description=Spotify and LastFM likes syncer
+After=network-online.target
+
+[Service]
+Type=oneshot
+# syncer caches are stored in working directory
+WorkingDirectory={working_dir}
+Environment=PYTHONPATH={project_dir}
+{environment_file}ExecStart={python} {app} {command}
+"""
+
+TIMER_TEMPLATE = """[Unit]
+Description=Run Spotify and LastFM likes syncer {timer}
+
+[Timer]
+OnCalendar={timer}
+Persistent=true
+
+[Install]
+WantedBy=timers.target
+"""
+
+
+def render_units(timer: str, env_file: Optional[str] = None, pipeline: Optional[str] = None) -> Dict[str, str]:
+    """Returns unit file name -> content for current python, syncer location and working directory."""
+    app = os.path.abspath(sys.modules['__main__'].__file__)
+    environment_file = f'EnvironmentFile={os.path.abspath(env_file)}\n' if env_file else ''
+
+    return {
+        f'{UNIT_NAME}.service': SERVICE_TEMPLATE.format(
+            working_dir=os.getcwd(),
+            project_dir=os.path.dirname(os.path.dirname(app)),
+            environment_file=environment_file,
+            python=sys.executable,
+            app=app,
+            command=f'run {pipeline}' if pipeline else 'sync',
+        ),
+        f'{UNIT_NAME}.timer': TIMER_TEMPLATE.format(timer=timer),
+    }
+
+
+def install_units(units: Dict[str, str]):
+    os.makedirs(USER_UNITS_DIR, exist_ok=True)
+    for name, content in units.items():
+        with open(os.path.join(USER_UNITS_DIR, name), 'w') as f:
+            f.write(content)
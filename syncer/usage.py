@@ -0,0 +1,117 @@
+"""API calls accounting: calls are counted by service per hour and limited per sync run by MAX_CALLS_PER_RUN setting.
+
+    Calls of requests sessions are counted by `identity.new_session` ones (spotipy and other services use them),
+    pylast has no hooks for sent requests, so its request sending is wrapped.
+"""
+
+import threading
+from collections import Counter
+from datetime import datetime, timedelta
+from typing import Dict, Optional
+
+import pylast
+
+from services.errors import ErrorKind, ServiceError
+
+# calls to other hosts are counted under host name
+SERVICE_HOSTS = {
+    'ws.audioscrobbler.com': 'lastfm',
+    'libre.fm': 'librefm',
+    'api.spotify.com': 'spotify',
+    'accounts.spotify.com': 'spotify',
+    'api.listenbrainz.org': 'listenbrainz',
+    'musicbrainz.org': 'musicbrainz',
+}
+# hourly counts older than this are dropped from stored usage
+KEEP_DAYS = 31
+
+_original_download_response = pylast._Request._download_response
+_active_usage: Optional['ApiUsage'] = None
+
+
+class BudgetExhaustedError(ServiceError):
+    """Call over MAX_CALLS_PER_RUN budget, the call is not sent and the track stays pending for the next run."""
+
+    def __init__(self, service: str, budget: int) -> None:
+        super().__init__(f'{service} calls budget of this run is exhausted ({budget})', ErrorKind.RATE_LIMIT)
+        self.service = service
+
+
+def count_call(host: Optional[str]):
+    """Counts API call to the host in the installed usage, raises BudgetExhaustedError before over budget call."""
+    if _active_usage and host:
+        _active_usage.record(SERVICE_HOSTS.get(host, host))
+
+
+def _counting_download_response(request, *args, **kwargs):
+    # LastFM and Libre.fm networks keep (host, path) of their API
+    count_call(request.network.ws_server[0])
+    return _original_download_response(request, *args, **kwargs)
+
+
+def target_service(target: str) -> str:
+    """Returns service of sync target, e.g. `lastfm` for `lastfm-partner` (LastFM limits are per API key)."""
+    return target.split('-')[0]
+
+
+class ApiUsage:
+    def __init__(self, max_calls_per_run: Dict[str, int], logger) -> None:
+        self.max_calls_per_run = max_calls_per_run
+        self.logger = logger
+        self.run_calls = Counter()
+        # (service, hour) -> calls, not stored yet
+        self.new_calls = Counter()
+        self._lock = threading.Lock()
+
+    def install(self):
+        """Starts counting calls of this process, the latest installed usage gets them."""
+        global _active_usage
+        _active_usage = self
+        pylast._Request._download_response = _counting_download_response
+
+    def start_run(self):
+        with self._lock:
+            self.run_calls.clear()
+
+    def record(self, service: str):
+        with self._lock:
+            self.run_calls[service] += 1
+            self.new_calls[service, datetime.now().strftime('%Y-%m-%dT%H')] += 1
+            calls = self.run_calls[service]
+
+        budget = self.max_calls_per_run.get(service)
+        if budget is not None and calls > budget:
+            raise BudgetExhaustedError(service, budget)
+
+    def is_exhausted(self, service: str) -> bool:
+        budget = self.max_calls_per_run.get(service)
+        return budget is not None and self.run_calls[service] >= budget
+
+    def merge(self, stored: Dict[str, Dict[str, int]]) -> Dict[str, Dict[str, int]]:
+        """Adds not stored calls to stored hourly counts (service -> hour -> calls) and drops old hours."""
+        oldest_hour = (datetime.now() - timedelta(days=KEEP_DAYS)).strftime('%Y-%m-%dT%H')
+        with self._lock:
+            for (service, hour), calls in self.new_calls.items():
+                stored.setdefault(service, {})
+                stored[service][hour] = stored[service].get(hour, 0) + calls
+            self.new_calls.clear()
+
+        return {
+            service: {hour: calls for hour, calls in hours.items() if hour >= oldest_hour}
+            for service, hours in stored.items()
+        }
+
+    def summarize(self, stored: Dict[str, Dict[str, int]]) -> Dict[str, Dict]:
+        """Returns calls of every service in the last hour and day with per-run budget."""
+        now = datetime.now()
+        hour = now.strftime('%Y-%m-%dT%H')
+        day_ago = (now - timedelta(days=1)).strftime('%Y-%m-%dT%H')
+        return {
+            service: {
+                'last_hour': hours.get(hour, 0),
+                # current hour is partial, so `last_day` covers 24-25 hours
+                'last_day': sum(calls for hour_, calls in hours.items() if hour_ >= day_ago),
+                'max_calls_per_run': self.max_calls_per_run.get(service),
+            }
+            for service, hours in sorted(stored.items())
+        }
@@ -0,0 +1,94 @@
+import logging
+from typing import Dict, List
+
+import pytest
+
+from syncer.app import Syncer
+from syncer.model import Track
+
+# FILTERS_* settings which filter nothing
+FILTERS = {
+    'genres_include': [], 'genres_exclude': [], 'min_duration_secs': None, 'min_popularity': None, 'explicit': None,
+}
+
+
+def make_track(id: str, artist: str = 'Artist', name: str = 'Song', **fields) -> Dict:
+    return Track(id=id, artist=artist, name=name, **fields).dict()
+
+
+class FakeSpotifyService:
+    """Spotify account with given liked tracks, listed newest first like Spotify does."""
+
+    def __init__(self, tracks: List[Dict], account: str = 'default') -> None:
+        self.tracks = tracks
+        self.account = account
+
+    def get_liked_tracks(self) -> List[Dict]:
+        return list(self.tracks)
+
+
+class FakeTargetService:
+    """Target service which has every track, tracks are `<artist> - <song name>` strings."""
+
+    def __init__(self) -> None:
+        self.loved: List[str] = []
+        self.unloved: List[str] = []
+
+    def get_track(self, artist: str, song_name: str) -> str:
+        return f'{artist} - {song_name}'
+
+    def like_track(self, track: str):
+        self.loved.append(track)
+
+    def unlike_track(self, track: str):
+        self.unloved.append(track)
+
+    def dump_track(self, track: str) -> str:
+        return track
+
+    def load_track(self, track: str) -> str:
+        return track
+
+
+@pytest.fixture
+def make_syncer(tmp_path, monkeypatch):
+    """Returns factory of syncers with fake services and state files in temporary working directory."""
+    monkeypatch.chdir(tmp_path)
+    # syncer expects LastFM cache file to exist
+    (tmp_path / '.cache_processed').write_text('[]')
+    (tmp_path / '.cache_processed').chmod(0o600)
+
+    def make(tracks: List[Dict] = (), **options) -> Syncer:
+        return Syncer(**{
+            'lastfm_service': FakeTargetService(),
+            'spotify_service': FakeSpotifyService(list(tracks)),
+            'extra_spotify_services': [],
+            'extra_sources': [],
+            'mpd_service': None,
+            'extra_targets': {},
+            'pipelines': {},
+            'track_hook_path': None,
+            'hooks': {},
+            'love_top_min_plays': 10,
+            'matchers': ['exact'],
+            'rewrite_rules': [],
+            'confirm_confidence': 0.0,
+            'min_confidence': None,
+            'not_found_retry_days': 7,
+            'search_cache_days': 30,
+            'filters': FILTERS,
+            'slow_love_interval_secs': 0.0,
+            'read_only': False,
+            'ntfy_url': None,
+            'heartbeat_url': None,
+            'sentry_dsn': None,
+            'max_calls_per_run': {},
+            'backdate_scrobbles': False,
+            'liked_since_days': None,
+            'mass_removal_ratio': 0.5,
+            'abort_on_mass_removal': False,
+            'logger': logging.getLogger('syncer-test'),
+            **options,
+        })
+
+    return make
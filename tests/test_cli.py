@@ -0,0 +1,33 @@
+import json
+
+import pytest
+
+from syncer.cli import read_loved_export
+
+
+@pytest.mark.parametrize('data', [
+    # lastfm-backup pages
+    [{'lovedtracks': {'track': [
+        {'artist': {'name': 'Artist'}, 'name': 'Song'},
+        {'artist': {'#text': 'Other Artist'}, 'name': 'Other Song'},
+    ]}}],
+    {'lovedtracks': {'track': [{'artist': 'Artist', 'name': 'Song'}, {'artist': 'Other Artist', 'name': 'Other Song'}]}},
+    [{'artist': 'Artist', 'name': 'Song'}, {'artist': 'Other Artist', 'name': 'Other Song'}],
+])
+def test_read_json_loved_export(tmp_path, data):
+    path = tmp_path / 'loved.json'
+    path.write_text(json.dumps(data))
+
+    assert read_loved_export(str(path)) == [('Artist', 'Song'), ('Other Artist', 'Other Song')]
+
+
+@pytest.mark.parametrize('content', [
+    'Artist,Song\nOther Artist,"Other Song, Part 1"\n',
+    'date,Artist,Track\n2021-10-01,Artist,Song\n\n2021-10-02,Other Artist,"Other Song, Part 1"\n',
+    'artist,title\nArtist,Song\nOther Artist,"Other Song, Part 1"\n',
+])
+def test_read_csv_loved_export(tmp_path, content):
+    path = tmp_path / 'loved.csv'
+    path.write_text(content)
+
+    assert read_loved_export(str(path)) == [('Artist', 'Song'), ('Other Artist', 'Other Song, Part 1')]
@@ -0,0 +1,50 @@
+import pytest
+
+from syncer.config import get_setting, set_setting
+
+# settings without defaults, the rest are read from .env of working directory
+REQUIRED_ENV = {
+    'LASTFM_USER': 'user',
+    'LASTFM_PASSWORD': 'password',
+    'LASTFM_API_KEY': 'api-key',
+    'LASTFM_API_SECRET': 'api-secret',
+    'SPOTIFY_CLIENT_ID': 'client-id',
+}
+
+
+@pytest.fixture(autouse=True)
+def settings_env(tmp_path, monkeypatch):
+    for name, value in REQUIRED_ENV.items():
+        monkeypatch.setenv(name, value)
+    for name in ('NOT_FOUND_RETRY_DAYS', 'FILTERS_MIN_POPULARITY', 'FILTERS_GENRES_INCLUDE'):
+        monkeypatch.delenv(name, raising=False)
+    monkeypatch.chdir(tmp_path)
+
+
+def test_set_setting_round_trip():
+    assert set_setting('not_found_retry_days', '3') == 'NOT_FOUND_RETRY_DAYS'
+    assert get_setting('not_found_retry_days') == 3
+
+
+def test_set_nested_setting_round_trip():
+    set_setting('filters.min_popularity', '20')
+    set_setting('filters.genres_include', '["post rock", "jazz"]')
+
+    assert get_setting('filters.min_popularity') == 20
+    assert get_setting('filters.genres_include') == ['post rock', 'jazz']
+
+
+def test_set_setting_replaces_previous_value():
+    set_setting('not_found_retry_days', '3')
+    set_setting('not_found_retry_days', '5')
+
+    assert get_setting('not_found_retry_days') == 5
+    with open('.env') as f:
+        assert f.read().count('NOT_FOUND_RETRY_DAYS=') == 1
+
+
+def test_environment_overrides_env_file(monkeypatch):
+    set_setting('not_found_retry_days', '3')
+    monkeypatch.setenv('NOT_FOUND_RETRY_DAYS', '10')
+
+    assert get_setting('not_found_retry_days') == 10
@@ -0,0 +1,75 @@
+from syncer.matching import find_duplicates, is_same_recording
+
+from tests.conftest import make_track
+
+
+def test_is_same_recording_by_isrc():
+    track = make_track('1', name='Song', isrc='USXXX0000001')
+    other = make_track('2', name='Another Name', isrc='USXXX0000001')
+
+    assert is_same_recording(track, other)
+
+
+def test_is_same_recording_ignores_rerelease_suffixes():
+    album = make_track('1', name='Song', duration_ms=200000)
+    remaster = make_track('2', name='Song - 2011 Remaster', duration_ms=201000)
+    single = make_track('3', name='Song (Single Version)')
+
+    assert is_same_recording(album, remaster)
+    # unknown duration is not compared
+    assert is_same_recording(album, single)
+
+
+def test_is_same_recording_keeps_other_recordings_apart():
+    album = make_track('1', name='Song', duration_ms=200000)
+
+    assert not is_same_recording(album, make_track('2', name='Song - Live Version', duration_ms=200000))
+    assert not is_same_recording(album, make_track('3', name='Song - 2011 Remaster', duration_ms=260000))
+
+
+def test_find_duplicates_keeps_earliest_liked_track():
+    remaster = make_track('new', name='Song - 2011 Remaster', added_at='2021-05-01T00:00:00Z')
+    album = make_track('old', name='Song', added_at='2020-01-01T00:00:00Z')
+
+    duplicates = find_duplicates([remaster, album], synced=[])
+
+    assert list(duplicates) == ['new']
+    assert duplicates['new']['id'] == 'old'
+
+
+def test_find_duplicates_keeps_synced_track():
+    album = make_track('old', name='Song', added_at='2021-05-01T00:00:00Z')
+    remaster = make_track('new', name='Song - 2011 Remaster', added_at='2020-01-01T00:00:00Z')
+
+    duplicates = find_duplicates([album, remaster], synced=[album])
+
+    assert list(duplicates) == ['new']
+
+
+def test_sync_skips_newly_liked_remaster_of_synced_track(make_syncer):
+    album = make_track('album', name='Song', added_at='2020-01-01T00:00:00Z')
+    syncer = make_syncer([album])
+    syncer.sync()
+
+    remaster = make_track('remaster', name='Song - 2011 Remaster', added_at='2021-05-01T00:00:00Z')
+    # Spotify lists the newest likes first
+    syncer.spotify_service.tracks = [remaster, album]
+    summary = syncer.sync()
+
+    assert syncer.lastfm_service.loved == ['Artist - Song']
+    assert summary['duplicates'] == [
+        {'track_id': 'remaster', 'artist': 'Artist', 'name': 'Song - 2011 Remaster', 'duplicate_of': 'album'},
+    ]
+
+
+def test_sync_skips_remaster_of_synced_track_not_listed_by_since_days_sync(make_syncer):
+    album = make_track('album', name='Song', added_at='2020-01-01T00:00:00Z')
+    syncer = make_syncer([album])
+    syncer.sync()
+
+    # older likes are not fetched
+    syncer.spotify_service.tracks = [make_track('remaster', name='Song - 2011 Remaster')]
+    summary = syncer.sync()
+
+    assert syncer.lastfm_service.loved == ['Artist - Song']
+    assert [duplicate['duplicate_of'] for duplicate in summary['duplicates']] == ['album']
@@ -0,0 +1,24 @@
+import pylast
+import pytest
+from pydantic import ValidationError
+
+from services.errors import ErrorKind, ServiceError, classify_error
+from syncer.model import Track
+
+
+def make_validation_error() -> ValidationError:
+    with pytest.raises(ValidationError) as error:
+        Track(id='1', artist='Artist', name=' ')
+    return error.value
+
+
+def test_service_data_errors_are_classified_as_data():
+    assert classify_error(make_validation_error()) == ErrorKind.DATA
+    assert classify_error(ServiceError('broken response', ErrorKind.DATA)) == ErrorKind.DATA
+    # unknown LastFM error codes
+    assert classify_error(pylast.WSError(None, '8', 'Operation failed')) == ErrorKind.DATA
+
+
+@pytest.mark.parametrize('error', [KeyError('artist'), ValueError('bug'), TypeError('bug')])
+def test_programming_errors_are_unknown(error):
+    assert classify_error(error) == ErrorKind.UNKNOWN
@@ -0,0 +1,44 @@
+import pytest
+
+from services.bandcamp import BandcampService
+from services.qobuz import QobuzService
+
+from tests.conftest import FILTERS, make_track
+
+
+@pytest.fixture
+def syncer(make_syncer):
+    return make_syncer()
+
+
+def filter_track(syncer, track, **filters):
+    return syncer._filter_track(syncer.spotify_service, track, {}, {**FILTERS, **filters})
+
+
+def test_spotify_track_is_filtered(syncer):
+    track = make_track('1', duration_ms=30000, popularity=10, explicit=False)
+
+    assert filter_track(syncer, track, min_duration_secs=60) == 'shorter than 60 seconds'
+    assert filter_track(syncer, track, min_popularity=20) == 'popularity is lower than 20'
+    assert filter_track(syncer, track, explicit='only') == 'not explicit'
+    assert filter_track(syncer, make_track('2', explicit=True), explicit='skip') == 'explicit'
+
+
+def test_unknown_track_metadata_is_not_filtered(syncer):
+    bandcamp_track = BandcampService._to_track({'band_name': 'Artist'}, {'track_id': 1, 'title': 'Song'})
+
+    assert filter_track(syncer, bandcamp_track, min_duration_secs=60) is None
+    assert filter_track(syncer, bandcamp_track, min_popularity=20) is None
+    assert filter_track(syncer, bandcamp_track, explicit='only') is None
+    assert filter_track(syncer, bandcamp_track, explicit='skip') is None
+
+
+def test_known_extra_source_metadata_is_filtered(syncer):
+    qobuz_track = QobuzService._to_track(
+        {'id': 1, 'title': 'Song', 'performer': {'name': 'Artist'}, 'duration': 30, 'parental_warning': True},
+    )
+
+    assert filter_track(syncer, qobuz_track, min_duration_secs=60) == 'shorter than 60 seconds'
+    assert filter_track(syncer, qobuz_track, explicit='skip') == 'explicit'
+    # Qobuz has no popularity
+    assert filter_track(syncer, qobuz_track, min_popularity=20) is None
@@ -0,0 +1,112 @@
+import logging
+
+import pytest
+
+from services.errors import ErrorKind, ServiceError
+from syncer.matching import FeaturingMatcher, MatcherChain, duplicate_name_key, normalize_song_name
+
+logger = logging.getLogger(__name__)
+
+
+class FakeService:
+    """Target service which has every track, tracks are `<artist> - <song name>` strings."""
+
+    def __init__(self) -> None:
+        self.searched = []
+
+    def get_track(self, artist: str, song_name: str) -> str:
+        self.searched.append((artist, song_name))
+        return f'{artist} - {song_name}'
+
+    def dump_track(self, track: str) -> str:
+        return track
+
+    def load_track(self, track: str) -> str:
+        return track
+
+
+def test_duplicate_name_key_keeps_other_recordings_apart():
+    assert normalize_song_name('Song - Live Version') == normalize_song_name('Song') == 'song'
+
+    assert duplicate_name_key('Artist', 'Song - Live Version') != duplicate_name_key('Artist', 'Song')
+    assert duplicate_name_key('Artist', 'Song (Acoustic Version)') != duplicate_name_key('Artist', 'Song')
+    assert duplicate_name_key('ARTIST', 'Song - 2011 Remaster') == duplicate_name_key('Artist', 'Song')
+
+
+def test_cache_key_keeps_other_recordings_apart():
+    service = FakeService()
+
+    studio_key = MatcherChain.get_cache_key(service, {'artist': 'Artist', 'name': 'Song'})
+    live_key = MatcherChain.get_cache_key(service, {'artist': 'Artist', 'name': 'Song - Live Version'})
+    remaster_key = MatcherChain.get_cache_key(service, {'artist': 'Artist', 'name': 'Song - 2011 Remaster'})
+
+    assert studio_key != live_key
+    assert studio_key == remaster_key
+
+
+def test_cached_match_is_not_used_for_other_recording():
+    service = FakeService()
+    chain = MatcherChain(['exact'], logger)
+
+    chain.match(service, {'artist': 'Artist', 'name': 'Song'})
+    found = chain.match(service, {'artist': 'Artist', 'name': 'Song - Live Version'})
+
+    assert found.target_track == 'Artist - Song - Live Version'
+    assert service.searched == [('Artist', 'Song'), ('Artist', 'Song - Live Version')]
+
+
+class FailingService(FakeService):
+    def __init__(self, error: Exception) -> None:
+        super().__init__()
+        self.error = error
+
+    def get_track(self, artist: str, song_name: str) -> str:
+        raise self.error
+
+
+def test_not_found_track_is_not_matched():
+    attempts = []
+    error = ServiceError('track is not found', ErrorKind.NOT_FOUND)
+
+    assert MatcherChain(['exact'], logger).match(FailingService(error), {'artist': 'A', 'name': 'S'}, attempts) is None
+    assert attempts == [{'matcher': 'exact', 'result': 'error: track is not found'}]
+
+
+def test_bug_fails_track_instead_of_not_found():
+    chain = MatcherChain(['exact'], logger)
+
+    with pytest.raises(KeyError):
+        chain.match(FailingService(KeyError('name')), {'artist': 'Artist', 'name': 'Song'})
+    assert chain.cache == {}
+
+
+@pytest.mark.parametrize('artist, song_name, candidates', [
+    ('Artist', 'Song (feat. Guest)', [('Artist', 'Song'), ('Artist feat. Guest', 'Song')]),
+    ('Artist', 'Song [ft. Guest]', [('Artist', 'Song'), ('Artist feat. Guest', 'Song')]),
+    ('Artist feat. Guest', 'Song', [('Artist', 'Song'), ('Artist', 'Song (feat. Guest)')]),
+    ('Artist', 'Song', []),
+])
+def test_featuring_candidates(artist, song_name, candidates):
+    assert FeaturingMatcher.get_candidates(artist, song_name) == candidates
+
+
+class MissingTracksService(FakeService):
+    """Target service without the given tracks."""
+
+    def __init__(self, *missing) -> None:
+        super().__init__()
+        self.missing = missing
+
+    def get_track(self, artist: str, song_name: str) -> str:
+        if (artist, song_name) in self.missing:
+            raise ServiceError('track is not found', ErrorKind.NOT_FOUND)
+        return super().get_track(artist, song_name)
+
+
+def test_featuring_matcher_tries_next_candidate():
+    service = MissingTracksService(('Artist', 'Song'))
+
+    found = FeaturingMatcher(logger).match(service, {'artist': 'Artist', 'name': 'Song (feat. Guest)'})
+
+    assert found.target_track == 'Artist feat. Guest - Song'
+    assert FeaturingMatcher(logger).match(service, {'artist': 'Artist', 'name': 'Song'}) is None
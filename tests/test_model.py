@@ -0,0 +1,19 @@
+import pytest
+from pydantic import ValidationError
+
+from syncer.model import Track
+
+
+def test_track_names_are_cleaned():
+    track = Track(id='1', artist=' Artist ', name='Cafe\u0301')
+
+    assert (track.artist, track.name) == ('Artist', 'Caf\u00e9')
+    with pytest.raises(ValidationError):
+        Track(id='1', artist='Artist', name='  ')
+
+
+def test_track_is_immutable():
+    track = Track(id='1', artist='Artist', name='Song', external_ids={'isrc': 'USXXX0000001'})
+
+    with pytest.raises(TypeError):
+        track.name = 'Other'
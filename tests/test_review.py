@@ -0,0 +1,15 @@
+from tests.conftest import make_track
+
+
+def test_rejected_match_is_not_cached(make_syncer):
+    # exact matches have confidence 1.0, so every match is queued for review
+    syncer = make_syncer([make_track('1')], min_confidence=1.1)
+    syncer.sync()
+    assert [match['candidate'] for match in syncer.list_review_queue()] == ['Artist - Song']
+    assert syncer._load_search_cache()
+
+    syncer.reject_reviewed(['1'])
+
+    assert syncer.list_review_queue() == []
+    assert syncer._load_search_cache() == {}
+    assert [track['track_id'] for track in syncer.list_unmatched()] == ['1']
@@ -0,0 +1,44 @@
+import pytest
+
+from syncer.cli import print_runs
+
+from tests.conftest import make_track
+
+
+class InterruptingSpotifyService:
+    account = 'default'
+
+    def get_liked_tracks(self):
+        yield make_track('1')
+        raise KeyboardInterrupt
+
+
+def test_interrupted_sync_is_stored_with_error(make_syncer):
+    syncer = make_syncer(spotify_service=InterruptingSpotifyService())
+
+    with pytest.raises(KeyboardInterrupt):
+        syncer.sync()
+
+    [run] = syncer.list_runs()
+    assert run['error'] == 'interrupted'
+    assert 'summary' not in run
+
+
+def test_runs_list_prints_interrupted_runs(capsys):
+    runs = [
+        {'run_id': 'new', 'started_at': '2021-10-02T03:00:00', 'duration_secs': 1.0, 'error': 'interrupted'},
+        # stored before interrupted runs got error
+        {'run_id': 'old', 'started_at': '2021-10-01T03:00:00', 'duration_secs': 2.0},
+        {
+            'run_id': 'done', 'started_at': '2021-10-03T03:00:00', 'duration_secs': 3.0,
+            'summary': {'loved': {'lastfm': 2}, 'phases': {'fetch': 0.5}},
+        },
+    ]
+
+    print_runs(runs)
+
+    assert capsys.readouterr().out.splitlines() == [
+        'new  2021-10-02T03:00:00  1.0s  failed: interrupted  ',
+        'old  2021-10-01T03:00:00  2.0s  interrupted  ',
+        "done  2021-10-03T03:00:00  3.0s  loved: {'lastfm': 2}  fetch 0.5s",
+    ]
@@ -0,0 +1,42 @@
+import os
+from datetime import datetime
+
+import pytest
+
+from syncer.cli import get_next_runs
+
+
+def test_last_scheduled_run_round_trip(make_syncer):
+    syncer = make_syncer()
+    run_at = datetime(2021, 10, 1, 3, 0)
+
+    syncer.dump_last_scheduled_run(run_at)
+    syncer.dump_last_scheduled_run(datetime(2021, 10, 1, 4, 0), pipeline='family')
+
+    assert syncer.load_last_scheduled_run() == run_at
+    assert syncer.load_last_scheduled_run('family') == datetime(2021, 10, 1, 4, 0)
+    assert syncer.load_last_scheduled_run('other') is None
+    assert oct(os.stat('.last_scheduled_run').st_mode & 0o777) == '0o600'
+
+
+def test_last_scheduled_run_of_older_versions_is_read(make_syncer):
+    with open('.last_scheduled_run', 'w') as f:
+        f.write('2021-10-01T03:00:00\n')
+
+    assert make_syncer().load_last_scheduled_run() == datetime(2021, 10, 1, 3, 0)
+
+
+def test_last_scheduled_run_is_not_stored_in_read_only_mode(make_syncer):
+    with pytest.raises(Exception, match='read-only'):
+        make_syncer(read_only=True).dump_last_scheduled_run(datetime(2021, 10, 1, 3, 0))
+
+
+def test_next_runs_follow_last_scheduled_runs(make_syncer):
+    syncer = make_syncer(pipelines={'family': {'schedule': '0 * * * *'}, 'manual': {'schedule': None}})
+    syncer.dump_last_scheduled_run(datetime(2021, 10, 1, 3, 0))
+    syncer.dump_last_scheduled_run(datetime(2021, 10, 1, 5, 0), pipeline='family')
+
+    assert get_next_runs(syncer, '0 * * * *') == {
+        'sync': datetime(2021, 10, 1, 4, 0),
+        'pipeline family': datetime(2021, 10, 1, 6, 0),
+    }
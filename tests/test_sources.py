@@ -0,0 +1,25 @@
+import logging
+
+from pydantic import SecretStr
+
+from services.qobuz import QobuzService
+from services.soundcloud import SoundCloudService
+
+
+def test_source_track_ids_are_prefixed():
+    track = SoundCloudService._to_track({'id': 1, 'title': 'Song', 'user': {'username': 'Artist'}})
+
+    assert track['id'] == 'soundcloud:1'
+    assert QobuzService.get_track_id(1) == 'qobuz:1'
+
+
+def test_malformed_source_tracks_are_skipped():
+    service = QobuzService('app', SecretStr('token'), logging.getLogger('test'))
+    items = [
+        {'id': 1, 'title': 'Song', 'performer': {'name': 'Artist'}},
+        {'id': 2, 'title': 'No artist'},
+    ]
+
+    tracks = list(service._to_tracks(items, service._to_track))
+
+    assert [track['id'] for track in tracks] == ['qobuz:1']
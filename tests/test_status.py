@@ -0,0 +1,35 @@
+from syncer.cli import print_status
+
+
+def make_status(last_run):
+    return {
+        'spotify_accounts': ['default'],
+        'targets': ['lastfm'],
+        'synced': {'lastfm': 10},
+        'unmatched': {'lastfm': 1},
+        'pending': None,
+        'last_run': last_run,
+        'running': [],
+        'api_usage': {},
+    }
+
+
+def test_status_prints_interrupted_last_run(capsys):
+    # stored before interrupted runs got error
+    last_run = {'run_id': 'abc', 'started_at': '2021-10-01T03:00:00', 'finished_at': '2021-10-01T03:01:00'}
+
+    print_status(make_status(last_run), next_runs={})
+
+    assert 'last run: abc at 2021-10-01T03:00:00, interrupted' in capsys.readouterr().out.splitlines()
+
+
+def test_status_prints_last_run_result(capsys):
+    failed = {'run_id': 'abc', 'started_at': '2021-10-01T03:00:00', 'error': 'interrupted'}
+    finished = {'run_id': 'def', 'started_at': '2021-10-02T03:00:00', 'summary': {'loved': {'lastfm': 2}}}
+
+    print_status(make_status(failed), next_runs={})
+    print_status(make_status(finished), next_runs={})
+
+    lines = capsys.readouterr().out.splitlines()
+    assert 'last run: abc at 2021-10-01T03:00:00, failed: interrupted' in lines
+    assert "last run: def at 2021-10-02T03:00:00, loved: {'lastfm': 2}" in lines
@@ -0,0 +1,31 @@
+import pytest
+
+from tests.conftest import make_track
+
+
+@pytest.fixture
+def syncer(make_syncer):
+    syncer = make_syncer([make_track('1', name='Song 1'), make_track('2', name='Song 2'), make_track('3', name='Song 3')])
+    syncer.sync()
+    # search cache expired and the same names are matched to other tracks now, e.g. after LastFM corrections
+    syncer.lastfm_service.get_track = lambda artist, song_name: f'{artist} - {song_name} (other)'
+    syncer._dump_search_cache({})
+    return syncer
+
+
+def test_undo_unloves_loved_tracks(syncer):
+    [run] = syncer.list_runs()
+
+    syncer.undo_run(run['run_id'])
+
+    assert sorted(syncer.lastfm_service.unloved) == ['Artist - Song 1', 'Artist - Song 2', 'Artist - Song 3']
+
+
+def test_prune_and_restore_love_loved_tracks(syncer):
+    syncer.spotify_service.tracks = syncer.spotify_service.tracks[1:]
+
+    syncer.prune_state(unlove=True)
+    syncer.restore_pruned()
+
+    assert syncer.lastfm_service.unloved == ['Artist - Song 1']
+    assert syncer.lastfm_service.loved[-1] == 'Artist - Song 1'